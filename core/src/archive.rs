@@ -0,0 +1,216 @@
+use std::io::{self, Cursor, Read};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use crate::cartridge::MAX_ROM_SIZE;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+/// File extensions of Game Boy ROMs, used to find the ROMs within an archive.
+pub const ROM_EXTENSIONS: [&str; 3] = ["gb", "gbc", "sgb"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Gzip,
+}
+
+impl ArchiveFormat {
+    /// Identify the format of an archive from its header, returning `None` if the data is not an archive (e.g., an
+    /// uncompressed ROM).
+    pub fn detect(data: &[u8]) -> Option<ArchiveFormat> {
+        if data.starts_with(ZIP_MAGIC) {
+            Some(ArchiveFormat::Zip)
+        } else if data.starts_with(GZIP_MAGIC) {
+            Some(ArchiveFormat::Gzip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the given file name has the extension of a Game Boy ROM.
+pub fn is_rom_name(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, ext)| {
+        ROM_EXTENSIONS
+            .iter()
+            .any(|rom_ext| ext.eq_ignore_ascii_case(rom_ext))
+    })
+}
+
+/// List the names of the ROMs within a ZIP archive, in the order they are stored. A gzip archive holds a single file so
+/// gives only the original name of that file (or an empty string if the archive does not record it).
+pub fn rom_entries(data: &[u8]) -> io::Result<Vec<String>> {
+    match ArchiveFormat::detect(data) {
+        Some(ArchiveFormat::Zip) => {
+            // iterate by index as file_names() does not preserve the order in which the files are stored
+            let mut zip = ZipArchive::new(Cursor::new(data))?;
+            let mut names = Vec::new();
+            for index in 0..zip.len() {
+                let file = zip.by_index_raw(index)?;
+                if is_rom_name(file.name()) {
+                    names.push(file.name().to_string());
+                }
+            }
+            Ok(names)
+        }
+        Some(ArchiveFormat::Gzip) => {
+            let name = GzDecoder::new(data)
+                .header()
+                .and_then(|header| header.filename())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_default();
+            Ok(vec![name])
+        }
+        None => Err(not_an_archive()),
+    }
+}
+
+/// Decompress the entry of the given name from a ZIP archive (the name is ignored for gzip archives).
+pub fn read_entry(data: &[u8], name: &str) -> io::Result<Vec<u8>> {
+    match ArchiveFormat::detect(data) {
+        Some(ArchiveFormat::Zip) => {
+            let mut zip = ZipArchive::new(Cursor::new(data))?;
+            let file = zip.by_name(name)?;
+            if file.size() > MAX_ROM_SIZE as u64 {
+                return Err(too_large());
+            }
+            read_limited(file)
+        }
+        Some(ArchiveFormat::Gzip) => read_limited(GzDecoder::new(data)),
+        None => Err(not_an_archive()),
+    }
+}
+
+/// Decompress the first ROM within an archive, or return the data unchanged if it is not an archive.
+pub fn read_rom(data: Vec<u8>) -> io::Result<Vec<u8>> {
+    if ArchiveFormat::detect(&data).is_none() {
+        return Ok(data);
+    }
+
+    let entries = rom_entries(&data)?;
+    let name = entries.first().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "archive does not contain a .gb, .gbc, or .sgb file",
+        )
+    })?;
+    read_entry(&data, name)
+}
+
+/// Read until the end of a decompressed entry, failing should it exceed [`MAX_ROM_SIZE`] (rather than trusting the size
+/// recorded by the archive, which may not match the data).
+fn read_limited(reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    reader
+        .take(MAX_ROM_SIZE as u64 + 1)
+        .read_to_end(&mut contents)?;
+    if contents.len() > MAX_ROM_SIZE {
+        return Err(too_large());
+    }
+    Ok(contents)
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "archive entry is larger than any Game Boy ROM",
+    )
+}
+
+fn not_an_archive() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "not a ZIP or gzip archive")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression, GzBuilder};
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, contents) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn rom_names() {
+        assert!(is_rom_name("Tetris.gb"));
+        assert!(is_rom_name("roms/Pokemon Yellow.GBC"));
+        assert!(!is_rom_name("readme.txt"));
+        assert!(!is_rom_name("gb"));
+    }
+
+    #[test]
+    fn zip_entries() {
+        // stored out of alphabetical order so that the first ROM stored is not simply the first by name
+        let data = zip(&[
+            ("readme.txt", b"hello"),
+            ("z.gbc", &[1, 2, 3]),
+            ("m.gb", &[6]),
+            ("a.gb", &[4, 5]),
+        ]);
+
+        assert_eq!(ArchiveFormat::detect(&data), Some(ArchiveFormat::Zip));
+        assert_eq!(rom_entries(&data).unwrap(), ["z.gbc", "m.gb", "a.gb"]);
+        assert_eq!(read_entry(&data, "a.gb").unwrap(), [4, 5]);
+        assert!(read_entry(&data, "c.gb").is_err());
+        assert_eq!(read_rom(data).unwrap(), [1, 2, 3]);
+
+        assert!(read_rom(zip(&[("readme.txt", b"hello")])).is_err());
+    }
+
+    #[test]
+    fn gzip() {
+        let mut encoder = GzBuilder::new()
+            .filename("game.gb")
+            .write(Vec::new(), Compression::default());
+        encoder.write_all(&[1, 2, 3]).unwrap();
+        let data = encoder.finish().unwrap();
+
+        assert_eq!(ArchiveFormat::detect(&data), Some(ArchiveFormat::Gzip));
+        assert_eq!(rom_entries(&data).unwrap(), ["game.gb"]);
+        assert_eq!(read_rom(data).unwrap(), [1, 2, 3]);
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(rom_entries(&encoder.finish().unwrap()).unwrap(), [""]);
+    }
+
+    #[test]
+    fn oversized() {
+        let contents = vec![0; MAX_ROM_SIZE + 1];
+        let data = zip(&[("big.gb", &contents)]);
+        assert_eq!(
+            read_rom(data).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&contents).unwrap();
+        assert_eq!(
+            read_rom(encoder.finish().unwrap()).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // a ROM of exactly the maximum size is accepted
+        let data = zip(&[("max.gb", &contents[1..])]);
+        assert_eq!(read_rom(data).unwrap().len(), MAX_ROM_SIZE);
+    }
+
+    #[test]
+    fn not_archive() {
+        assert_eq!(read_rom(vec![0, 1, 2]).unwrap(), [0, 1, 2]);
+        assert!(rom_entries(&[0, 1, 2]).is_err());
+    }
+}
@@ -1,12 +1,20 @@
-use crate::gpu::oam::{OAM_END, OAM_SIZE, OAM_START};
+use crate::cheats::Cheats;
+use crate::debugger::Debugger;
+use crate::dma::OamDma;
+use crate::gpu::oam::{OamCorruption, OAM_END, OAM_START};
 use crate::gpu::vram::{VRAM_END, VRAM_START};
 use crate::gpu::Gpu;
 use crate::interrupts::Interrupts;
+use crate::io::{io_register_mask, AUDIO_END, AUDIO_SIZE, AUDIO_START, IO_END, IO_START};
 use crate::joypad::Joypad;
 use crate::mbc::MemoryBankController;
+use crate::ram_init::{RamInit, RamRegion};
 use crate::serial::SerialTransfer;
+use crate::sgb::Sgb;
+use crate::speed::SpeedSwitch;
+use crate::symbols::SymbolTable;
 use crate::timer::Timer;
-use crate::Cycles;
+use crate::{Cycles, Model, M_CYCLE};
 
 const WRAM_START: u16 = 0xC000;
 const WRAM_END: u16 = 0xDFFF;
@@ -15,58 +23,244 @@ const WRAM_SIZE: usize = (WRAM_END - WRAM_START + 1) as usize;
 const ECHO_RAM_START: u16 = 0xE000;
 const ECHO_RAM_END: u16 = 0xFDFF;
 
+/// Size of each bank of cartridge RAM (mapped to 0xA000 to 0xBFFF).
+const CARTRIDGE_RAM_BANK_SIZE: usize = 0x2000;
+
 const HRAM_START: u16 = 0xFF80;
 const HRAM_END: u16 = 0xFFFE;
 const HRAM_SIZE: usize = (HRAM_END - HRAM_START + 1) as usize;
 
-const OAM_TRANSFER_PERIOD: Cycles = 640;
-
 /// Represents both general-purpose RAM (working RAM and high RAM) as well as manages certain components of the full
 /// system that are interacted with via the memory bus (the GPU, timer, interrupt system, serial, joypad, and OAM
-/// transfer).
+/// DMA).
 pub struct MemoryBus {
+    model: Model,
     mbc: Box<dyn MemoryBankController>,
     pub gpu: Gpu,
     timer: Timer,
     pub interrupts: Interrupts,
     pub serial: SerialTransfer,
     pub joypad: Joypad,
+    speed_switch: SpeedSwitch,
+    /// Set while the system clock is stopped by the STOP instruction.
+    stopped: bool,
     wram: [u8; WRAM_SIZE],
     hram: [u8; HRAM_SIZE],
-    oam_transfer_source: u8,
-    pending_oam_transfer: bool,
-    oam_transfer_clock: Cycles,
+    /// Audio registers and wave RAM (stored but not otherwise emulated).
+    audio: [u8; AUDIO_SIZE],
+    oam_dma: OamDma,
+    /// The Super Game Boy, present only when emulating the SGB model.
+    pub sgb: Option<Sgb>,
+    pub cheats: Cheats,
+    /// The frame at which GameShark cheats were last applied.
+    cheats_frame: u64,
+    pub debugger: Debugger,
+    pub symbols: SymbolTable,
 }
 
 impl MemoryBus {
-    pub fn new(mbc: Box<dyn MemoryBankController>) -> Self {
-        MemoryBus {
+    /// Create a memory bus with its RAM (including VRAM and cartridge RAM) initialised according to the given policy.
+    pub fn new(mbc: Box<dyn MemoryBankController>, model: Model, ram_init: RamInit) -> Self {
+        let mut bus = MemoryBus {
+            model,
             mbc,
             gpu: Gpu::new(),
             timer: Timer::new(),
             interrupts: Interrupts::new(),
             serial: SerialTransfer::new(),
-            joypad: Joypad::new(),
+            joypad: if model == Model::Sgb {
+                Joypad::with_sgb()
+            } else {
+                Joypad::new()
+            },
+            speed_switch: SpeedSwitch::new(),
+            stopped: false,
             wram: [0; WRAM_SIZE],
             hram: [0; HRAM_SIZE],
-            oam_transfer_source: 0,
-            pending_oam_transfer: false,
-            oam_transfer_clock: 0,
+            audio: [0; AUDIO_SIZE],
+            oam_dma: OamDma::new(),
+            sgb: (model == Model::Sgb).then(Sgb::new),
+            cheats: Cheats::new(),
+            cheats_frame: 0,
+            debugger: Debugger::new(),
+            symbols: SymbolTable::new(),
+        };
+
+        bus.init_ram(ram_init);
+        bus.init_cartridge_ram(ram_init);
+
+        bus
+    }
+
+    /// Reset all components to their power-on state while keeping the inserted cartridge (and the contents of its
+    /// RAM). Buttons held on the joypad remain held.
+    pub fn reset(&mut self, ram_init: RamInit) {
+        self.mbc.reset();
+        self.gpu = Gpu::new();
+        self.timer = Timer::new();
+        self.interrupts = Interrupts::new();
+        self.serial = SerialTransfer::new();
+        self.joypad.reset();
+        self.speed_switch = SpeedSwitch::new();
+        self.stopped = false;
+        self.audio = [0; AUDIO_SIZE];
+        self.oam_dma = OamDma::new();
+        self.sgb = (self.model == Model::Sgb).then(Sgb::new);
+        self.cheats_frame = 0;
+        self.init_ram(ram_init);
+    }
+
+    /// Replace the inserted cartridge, initialising the RAM of the new cartridge according to the given policy. Returns
+    /// the previously inserted cartridge. Note that this does not reset the rest of the system.
+    pub fn swap_cartridge(
+        &mut self,
+        mbc: Box<dyn MemoryBankController>,
+        ram_init: RamInit,
+    ) -> Box<dyn MemoryBankController> {
+        let previous = std::mem::replace(&mut self.mbc, mbc);
+        self.init_cartridge_ram(ram_init);
+        previous
+    }
+
+    fn init_ram(&mut self, ram_init: RamInit) {
+        ram_init.fill(&mut self.wram, RamRegion::Wram, self.model);
+        ram_init.fill(&mut self.hram, RamRegion::Hram, self.model);
+        ram_init.fill(self.gpu.vram.data_mut(), RamRegion::Vram, self.model);
+    }
+
+    fn init_cartridge_ram(&mut self, ram_init: RamInit) {
+        if let Some(ram) = self.mbc.ram_mut() {
+            ram_init.fill(ram, RamRegion::CartridgeRam, self.model);
         }
     }
 
     /// Update the components of the emulator interacted with via the memory bus given that a specified number of CPU
-    /// cycles have elapsed.
+    /// cycles have elapsed. While the system clock is stopped, only the joypad is updated.
     pub fn update(&mut self, cycles: Cycles) {
-        self.gpu.update(&mut self.interrupts, cycles);
+        self.joypad.update(&mut self.interrupts);
+
+        if self.stopped {
+            return;
+        }
+
+        // in double speed mode the GPU runs at the same speed while everything else runs twice as fast
+        let gpu_cycles = if self.speed_switch.double_speed {
+            cycles / 2
+        } else {
+            cycles
+        };
+
+        self.gpu.update(&mut self.interrupts, gpu_cycles);
+
+        if let Some(sgb) = &mut self.sgb {
+            sgb.update(&self.gpu.screen);
+        }
+
+        let frame = self.gpu.screen.frame_count();
+        if frame != self.cheats_frame {
+            self.cheats_frame = frame;
+            self.apply_ram_cheats();
+        }
+
         self.timer.update(&mut self.interrupts, cycles);
         self.serial.update();
-        self.update_oam_transfer(cycles);
+        self.update_oam_dma(cycles);
     }
 
+    /// Perform the writes of any enabled GameShark cheats. These are made regardless of any OAM DMA transfer in
+    /// progress, and those to a particular cartridge RAM bank regardless of whether that bank is mapped (or RAM enabled).
+    fn apply_ram_cheats(&mut self) {
+        // the cheats are moved out of the bus while writing as writes go through the bus
+        let cheats = std::mem::take(&mut self.cheats);
+
+        for (bank, addr, value) in cheats.ram_writes() {
+            match bank {
+                Some(bank) => {
+                    let offset = bank as usize * CARTRIDGE_RAM_BANK_SIZE + (addr - 0xA000) as usize;
+                    match self.mbc.ram_mut().and_then(|ram| ram.get_mut(offset)) {
+                        Some(byte) => *byte = value,
+                        None => log::trace!(
+                            "GameShark write to missing cartridge RAM bank {bank} ignored"
+                        ),
+                    }
+                }
+                None => self.write8_unrestricted(addr, value),
+            }
+        }
+
+        self.cheats = cheats;
+    }
+
+    /// Get the number of the cartridge ROM bank currently mapped to the given address (from 0x0000 to 0x7FFF).
+    pub fn rom_bank(&self, addr: u16) -> u16 {
+        self.mbc.rom_bank(addr)
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Whether the CPU is running in CGB double speed mode.
+    pub fn double_speed(&self) -> bool {
+        self.speed_switch.double_speed
+    }
+
+    /// Whether a CGB speed switch has been requested via the KEY1 register (to be performed by the STOP instruction).
+    pub fn speed_switch_armed(&self) -> bool {
+        self.model == Model::Cgb && self.speed_switch.armed
+    }
+
+    /// Perform a CGB speed switch between normal and double speed modes.
+    pub fn switch_speed(&mut self) {
+        self.speed_switch.switch();
+    }
+
+    /// Stop the system clock (including the timer and GPU) and blank the LCD. Called when the CPU enters STOP mode.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+        self.gpu.screen.blank();
+    }
+
+    /// Restart the system clock after it was stopped by [`MemoryBus::stop`].
+    pub fn resume(&mut self) {
+        self.stopped = false;
+    }
+
+    /// Reset the DIV register (and the internal counter used to increment it) to 0.
+    pub fn reset_divider(&mut self) {
+        self.timer.reset_divider();
+    }
+
+    /// Trigger the OAM corruption bug, present on DMG and SGB models, should the CPU access (or place on the address bus
+    /// via a 16-bit increment/decrement) an address in 0xFE00 to 0xFEFF while the PPU is searching OAM.
+    pub fn corrupt_oam(&mut self, addr: u16, access: OamCorruption) {
+        if self.model == Model::Cgb || !(OAM_START..=0xFEFF).contains(&addr) {
+            return;
+        }
+
+        if let Some(row) = self.gpu.oam_row_being_read() {
+            log::trace!("OAM corruption bug triggered by access to {addr:#06X} (row {row})");
+            self.gpu.oam.corrupt(row, access);
+        }
+    }
+
+    /// Read a byte as the CPU would. While an OAM DMA transfer is in progress, the CPU can only access HRAM and the IO
+    /// registers - reading OAM gives 0xFF while reading anywhere else gives the byte currently being transferred.
     pub fn read8(&self, addr: u16) -> u8 {
+        if self.oam_dma_conflict(addr) {
+            return match addr {
+                OAM_START..=OAM_END => 0xFF,
+                _ => self.oam_dma.bus_value,
+            };
+        }
+
+        self.read8_unrestricted(addr)
+    }
+
+    /// Read a byte without the restrictions placed on the CPU during OAM DMA.
+    fn read8_unrestricted(&self, addr: u16) -> u8 {
         match addr {
-            0x0000..=0x7FFF => self.mbc.read8(addr),
+            0x0000..=0x7FFF => self.cheats.patch_rom(addr, self.mbc.read8(addr)),
             VRAM_START..=VRAM_END => self.gpu.vram.read8(addr),
             0xA000..=0xBFFF => self.mbc.read8(addr),
             WRAM_START..=WRAM_END => self.wram[(addr - WRAM_START) as usize],
@@ -79,33 +273,9 @@ impl MemoryBus {
                 log::warn!("prohibited address {:#04X} read", addr);
                 0xFF
             }
-            0xFF00 => self.joypad.get_byte(),
-            0xFF01 => self.serial.data,
-            0xFF02 => self.serial.control,
-            0xFF04 => self.timer.divider,
-            0xFF05 => self.timer.counter,
-            0xFF06 => self.timer.modulo,
-            0xFF07 => self.timer.control,
-            0xFF0F => self.interrupts.flag,
-            0xFF10..=0xFF3F => 0, // TODO: audio
-            0xFF40 => self.gpu.lcd_control.0,
-            0xFF41 => self.gpu.lcd_status.0,
-            0xFF42 => self.gpu.viewport_y,
-            0xFF43 => self.gpu.viewport_x,
-            0xFF44 => self.gpu.lcd_y,
-            0xFF45 => self.gpu.ly_compare,
-            0xFF46 => self.oam_transfer_source,
-            0xFF47 => self.gpu.bg_palette_data.0,
-            0xFF48 => self.gpu.obj_palette_0_data.0,
-            0xFF49 => self.gpu.obj_palette_1_data.0,
-            0xFF4A => self.gpu.window_y,
-            0xFF4B => self.gpu.window_x_plus_7,
+            IO_START..=IO_END => self.read_io(addr),
             HRAM_START..=HRAM_END => self.hram[(addr - HRAM_START) as usize],
             0xFFFF => self.interrupts.enable,
-            _ => {
-                log::trace!("{:#04X}", addr);
-                0
-            }
         }
     }
 
@@ -115,7 +285,13 @@ impl MemoryBus {
         u16::from_be_bytes([msb, lsb])
     }
 
+    /// Write a byte as the CPU would. Writes outside of HRAM and the IO registers are ignored during OAM DMA.
     pub fn write8(&mut self, addr: u16, value: u8) {
+        if self.oam_dma_conflict(addr) {
+            log::trace!("write to {addr:#06X} ignored due to OAM DMA transfer in progress");
+            return;
+        }
+
         log::trace!(
             "at memory address {:#06X}, writing byte {:#04X} (replacing previous value {:#04X})",
             addr,
@@ -123,6 +299,11 @@ impl MemoryBus {
             self.read8(addr)
         );
 
+        self.write8_unrestricted(addr, value);
+    }
+
+    /// Write a byte without the restrictions placed on the CPU during OAM DMA.
+    fn write8_unrestricted(&mut self, addr: u16, value: u8) {
         match addr {
             0x0000..=0x7FFF => self.mbc.write8(addr, value),
             VRAM_START..=VRAM_END => self.gpu.vram.write8(addr, value),
@@ -134,7 +315,83 @@ impl MemoryBus {
             }
             OAM_START..=OAM_END => self.gpu.oam.write8(addr, value),
             0xFEA0..=0xFEFF => log::warn!("prohibited address {:#04X} written to", addr),
-            0xFF00 => self.joypad.set_byte(value),
+            IO_START..=IO_END => self.write_io(addr, value),
+            HRAM_START..=HRAM_END => self.hram[(addr - HRAM_START) as usize] = value,
+            0xFFFF => self.interrupts.enable = value,
+        }
+    }
+
+    pub fn write16(&mut self, addr: u16, value: u16) {
+        let [msb, lsb] = value.to_be_bytes();
+        self.write8(addr, lsb); // little endian so LSB first
+        self.write8(addr + 1, msb);
+    }
+
+    /// Read an IO register, with any unused or write-only bits reading as 1. Addresses that don't map to any register
+    /// read as 0xFF.
+    fn read_io(&self, addr: u16) -> u8 {
+        match io_register_mask(addr, self.model) {
+            Some(mask) => mask.read(self.read_io_register(addr)),
+            None => {
+                log::trace!("unmapped IO register {addr:#06X} read");
+                0xFF
+            }
+        }
+    }
+
+    /// Write an IO register, with bits that are read-only or unused retaining their previous value. Writes to addresses
+    /// that don't map to any register are ignored.
+    fn write_io(&mut self, addr: u16, value: u8) {
+        match io_register_mask(addr, self.model) {
+            Some(mask) if mask.writable != 0 => {
+                let value = mask.write(self.read_io_register(addr), value);
+                self.write_io_register(addr, value);
+            }
+            Some(_) => log::trace!("read-only IO register {addr:#06X} written to"),
+            None => log::trace!("unmapped IO register {addr:#06X} written to"),
+        }
+    }
+
+    /// Get the stored value of an IO register (see [`io_register_mask`] for which registers are mapped).
+    fn read_io_register(&self, addr: u16) -> u8 {
+        match addr {
+            0xFF00 => self.joypad.get_byte(),
+            0xFF01 => self.serial.data,
+            0xFF02 => self.serial.control,
+            0xFF04 => self.timer.divider,
+            0xFF05 => self.timer.counter,
+            0xFF06 => self.timer.modulo,
+            0xFF07 => self.timer.control,
+            0xFF0F => self.interrupts.flag,
+            AUDIO_START..=AUDIO_END => self.audio[(addr - AUDIO_START) as usize], // TODO: audio
+            0xFF40 => self.gpu.lcd_control.0,
+            0xFF41 => self.gpu.lcd_status.0,
+            0xFF42 => self.gpu.viewport_y,
+            0xFF43 => self.gpu.viewport_x,
+            0xFF44 => self.gpu.lcd_y,
+            0xFF45 => self.gpu.ly_compare,
+            0xFF46 => self.oam_dma.source,
+            0xFF47 => self.gpu.bg_palette_data.0,
+            0xFF48 => self.gpu.obj_palette_0_data.0,
+            0xFF49 => self.gpu.obj_palette_1_data.0,
+            0xFF4A => self.gpu.window_y,
+            0xFF4B => self.gpu.window_x_plus_7,
+            0xFF4D => self.speed_switch.get_byte(),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_io_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xFF00 => {
+                self.joypad.set_byte(value);
+                if let (Some(packet), Some(sgb)) = (self.joypad.take_sgb_packet(), &mut self.sgb) {
+                    sgb.receive_packet(packet, &self.gpu.screen);
+                    if sgb.players() != self.joypad.players() {
+                        self.joypad.set_players(sgb.players());
+                    }
+                }
+            }
             0xFF01 => self.serial.data = value,
             0xFF02 => self.serial.control = value,
             0xFF04 => self.timer.divider = value,
@@ -142,52 +399,35 @@ impl MemoryBus {
             0xFF06 => self.timer.modulo = value,
             0xFF07 => self.timer.control = value,
             0xFF0F => self.interrupts.flag = value,
-            0xFF10..=0xFF3F => {} // TODO: audio
+            AUDIO_START..=AUDIO_END => self.audio[(addr - AUDIO_START) as usize] = value, // TODO: audio
             0xFF40 => self.gpu.lcd_control.0 = value,
             0xFF41 => self.gpu.lcd_status.0 = value,
             0xFF42 => self.gpu.viewport_y = value,
             0xFF43 => self.gpu.viewport_x = value,
-            0xFF44 => {} // LCD Y is read-only
             0xFF45 => self.gpu.ly_compare = value,
-            0xFF46 => {
-                self.oam_transfer_source = value;
-                self.pending_oam_transfer = true;
-                self.oam_transfer_clock = 0;
-            }
+            0xFF46 => self.oam_dma.start(value),
             0xFF47 => self.gpu.bg_palette_data.0 = value,
             0xFF48 => self.gpu.obj_palette_0_data.0 = value,
             0xFF49 => self.gpu.obj_palette_1_data.0 = value,
             0xFF4A => self.gpu.window_y = value,
             0xFF4B => self.gpu.window_x_plus_7 = value,
-            HRAM_START..=HRAM_END => self.hram[(addr - HRAM_START) as usize] = value,
-            0xFFFF => self.interrupts.enable = value,
-            _ => log::trace!("{:#04X}", addr),
+            0xFF4D => self.speed_switch.set_byte(value),
+            _ => {}
         }
     }
 
-    pub fn write16(&mut self, addr: u16, value: u16) {
-        let [msb, lsb] = value.to_be_bytes();
-        self.write8(addr, lsb); // little endian so LSB first
-        self.write8(addr + 1, msb);
+    /// Whether a CPU access to the given address conflicts with an OAM DMA transfer in progress.
+    fn oam_dma_conflict(&self, addr: u16) -> bool {
+        self.oam_dma.active() && addr < IO_START
     }
 
-    fn update_oam_transfer(&mut self, cycles: Cycles) {
-        if self.pending_oam_transfer {
-            self.oam_transfer_clock += cycles;
-
-            if self.oam_transfer_clock >= OAM_TRANSFER_PERIOD {
-                self.perform_oam_transfer();
-                self.pending_oam_transfer = false;
+    fn update_oam_dma(&mut self, cycles: Cycles) {
+        for _ in 0..cycles / M_CYCLE {
+            if let Some((source_address, offset)) = self.oam_dma.cycle() {
+                let value = self.read8_unrestricted(source_address);
+                self.oam_dma.bus_value = value;
+                self.gpu.oam.write8(OAM_START + offset, value);
             }
         }
     }
-
-    fn perform_oam_transfer(&mut self) {
-        let start_address = self.oam_transfer_source as u16 * 0x100;
-
-        for (offset, addr) in (start_address..(start_address + OAM_SIZE as u16)).enumerate() {
-            let value = self.read8(addr);
-            self.gpu.oam.write8(OAM_START + offset as u16, value);
-        }
-    }
 }
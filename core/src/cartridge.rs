@@ -1,7 +1,20 @@
 use std::path::Path;
 use std::{fmt, fs, io};
 
+use crate::archive;
+use crate::patch::{self, PatchError};
+
 const TITLE_START: usize = 0x0134;
+const TITLE_LENGTH: usize = 16;
+const NEW_LICENSEE_CODE: usize = 0x0144;
+const OLD_LICENSEE_CODE: usize = 0x014B;
+/// Old licensee code indicating that the new licensee code should be used instead.
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+const NINTENDO_LICENSEE_CODE: u8 = 0x01;
+
+/// Largest ROM accepted when decompressing or patching (the largest cartridge ROM supported by any MBC is 8 MiB).
+/// Sizes given by archives and patches are checked against this before any memory is allocated for the ROM.
+pub const MAX_ROM_SIZE: usize = 8 * 1024 * 1024;
 
 pub struct Cartridge {
     data: Vec<u8>,
@@ -12,8 +25,41 @@ impl Cartridge {
         Cartridge { data }
     }
 
+    /// Load a ROM file, or the first ROM (`.gb`, `.gbc`, or `.sgb` file) within a ZIP or gzip archive.
     pub fn from_file(path: impl AsRef<Path>) -> io::Result<Cartridge> {
-        fs::read(path).map(Cartridge::from_data)
+        archive::read_rom(fs::read(path)?).map(Cartridge::from_data)
+    }
+
+    /// Load the ROM of the given name from a ZIP archive.
+    pub fn from_archive_entry(path: impl AsRef<Path>, name: &str) -> io::Result<Cartridge> {
+        archive::read_entry(&fs::read(path)?, name).map(Cartridge::from_data)
+    }
+
+    /// List the names of the ROMs within a ZIP or gzip archive (see [`archive::rom_entries`]).
+    pub fn archive_entries(path: impl AsRef<Path>) -> io::Result<Vec<String>> {
+        archive::rom_entries(&fs::read(path)?)
+    }
+
+    /// Apply the patch of the same name beside the given ROM file (e.g., `game.ips` for `game.gb` or `game.zip`) should
+    /// there be one.
+    pub fn apply_patch_beside(&mut self, rom_path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(patch_path) = patch::find_patch(rom_path) {
+            log::info!("applying patch {}", patch_path.display());
+            self.apply_patch_file(patch_path)?;
+        }
+        Ok(())
+    }
+
+    /// Apply an IPS, UPS, or BPS patch to the ROM.
+    pub fn apply_patch(&mut self, patch: &[u8]) -> Result<(), PatchError> {
+        self.data = patch::apply(patch, &self.data)?;
+        Ok(())
+    }
+
+    pub fn apply_patch_file(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let patch = fs::read(path)?;
+        self.apply_patch(&patch)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 
     pub fn read8(&self, addr: usize) -> u8 {
@@ -21,12 +67,32 @@ impl Cartridge {
     }
 
     pub fn game_title(&self) -> String {
-        self.data[TITLE_START..TITLE_START + 16]
+        self.title_bytes()
             .iter()
             .map_while(|c| (*c != 0).then_some(*c as char))
             .collect()
     }
 
+    /// Sum of all 16 bytes of the title area of the header (wrapping on overflow), as used by the CGB boot ROM to
+    /// identify DMG games.
+    pub fn title_checksum(&self) -> u8 {
+        self.title_bytes()
+            .iter()
+            .fold(0, |sum, b| sum.wrapping_add(*b))
+    }
+
+    /// Whether the game was published by Nintendo according to the licensee code in the header.
+    pub fn licensed_by_nintendo(&self) -> bool {
+        match self.data[OLD_LICENSEE_CODE] {
+            USE_NEW_LICENSEE_CODE => &self.data[NEW_LICENSEE_CODE..NEW_LICENSEE_CODE + 2] == b"01",
+            code => code == NINTENDO_LICENSEE_CODE,
+        }
+    }
+
+    fn title_bytes(&self) -> &[u8] {
+        &self.data[TITLE_START..TITLE_START + TITLE_LENGTH]
+    }
+
     pub fn cart_type(&self) -> CartridgeType {
         match self.data[0x147] {
             0x00 => CartridgeType::RomOnly,
@@ -0,0 +1,331 @@
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::parse::{self, ParseError};
+
+/// GameShark codes may only write to memory above the cartridge ROM.
+const GAMESHARK_MIN_ADDRESS: u16 = 0x8000;
+
+/// GameShark code type that writes to whichever memory is currently mapped at the address.
+const GAMESHARK_MAPPED: u8 = 0x01;
+
+/// GameShark code types from 0x80 to 0x8F write to cartridge RAM bank 0 to 15.
+const GAMESHARK_RAM_BANK: u8 = 0x80;
+
+/// A decoded cheat code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatCode {
+    /// Game Genie codes patch cartridge ROM - reads of the given address return the given value instead. If a compare
+    /// value is present, the patch only applies when the original value matches it (as ROM banking means the same
+    /// address may hold different data).
+    GameGenie {
+        address: u16,
+        value: u8,
+        compare: Option<u8>,
+    },
+    /// GameShark codes write a value to RAM at the start of every frame. The bank is the cartridge RAM bank to write
+    /// to (`None` to write to whichever memory is currently mapped at the address).
+    GameShark {
+        bank: Option<u8>,
+        address: u16,
+        value: u8,
+    },
+}
+
+/// Parses either a Game Genie code (`ABC-DEF` or `ABC-DEF-GHI`) or a GameShark code (`TTVVLLHH`) made up of
+/// hexadecimal digits.
+///
+/// A Game Genie code `AB` `C` `DE` `F` `G` `H` `I` gives the value `AB`, the address `(F ^ 0xF)CDE`, and (for 9 digit
+/// codes) the compare value `GI` rotated right by 2 bits and XORed with 0xBA. `H` is ignored.
+///
+/// A GameShark code gives the type `TT`, the value `VV`, and the address `HHLL`. Type `01` writes to the memory currently
+/// mapped at the address while type `8X` writes to bank `X` of cartridge RAM (regardless of which bank is mapped).
+/// Other types (such as the `9X` codes that select a CGB work RAM bank) are not supported.
+impl FromStr for CheatCode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason: &str| ParseError {
+            line: 0,
+            reason: format!("invalid cheat code '{s}': {reason}"),
+        };
+
+        let digits: Vec<u8> = s
+            .chars()
+            .filter(|c| *c != '-')
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(|| error("expected hexadecimal digits"))?;
+
+        let byte = |i: usize| (digits[i] << 4) | digits[i + 1];
+
+        match digits.len() {
+            6 | 9 => {
+                let address = ((digits[5] as u16 ^ 0xF) << 12)
+                    | ((digits[2] as u16) << 8)
+                    | ((digits[3] as u16) << 4)
+                    | digits[4] as u16;
+                let compare = (digits.len() == 9)
+                    .then(|| ((digits[6] << 4) | digits[8]).rotate_right(2) ^ 0xBA);
+
+                if address >= GAMESHARK_MIN_ADDRESS {
+                    return Err(error("Game Genie codes must patch cartridge ROM"));
+                }
+
+                Ok(CheatCode::GameGenie {
+                    address,
+                    value: byte(0),
+                    compare,
+                })
+            }
+            8 => {
+                let address = u16::from_le_bytes([byte(4), byte(6)]);
+
+                if address < GAMESHARK_MIN_ADDRESS {
+                    return Err(error("GameShark codes must write to RAM"));
+                }
+
+                let bank = match byte(0) {
+                    GAMESHARK_MAPPED => None,
+                    code_type if code_type & 0xF0 == GAMESHARK_RAM_BANK => {
+                        if !(0xA000..=0xBFFF).contains(&address) {
+                            return Err(error(
+                                "GameShark codes of type 8X must write to cartridge RAM",
+                            ));
+                        }
+                        Some(code_type & 0x0F)
+                    }
+                    _ => return Err(error("unsupported GameShark code type")),
+                };
+
+                Ok(CheatCode::GameShark {
+                    bank,
+                    address,
+                    value: byte(2),
+                })
+            }
+            _ => Err(error(
+                "expected 6 or 9 digits (Game Genie) or 8 digits (GameShark)",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub code: CheatCode,
+    pub description: String,
+    pub enabled: bool,
+}
+
+/// The cheats applied to the running game.
+#[derive(Debug, Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+    /// The enabled Game Genie codes as (address, value, compare) - kept separately so that ROM reads only need to check
+    /// whether this is empty when there are no such codes.
+    rom_patches: Vec<(u16, u8, Option<u8>)>,
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Cheats::default()
+    }
+
+    /// Load a list of cheats from a text file (see [`Cheats::from_str`] for the format).
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        parse::from_file(path)
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /// Add an enabled cheat, returning its index.
+    pub fn add(&mut self, code: CheatCode, description: impl Into<String>) -> usize {
+        self.cheats.push(Cheat {
+            code,
+            description: description.into(),
+            enabled: true,
+        });
+        self.update_rom_patches();
+        self.cheats.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Cheat {
+        let cheat = self.cheats.remove(index);
+        self.update_rom_patches();
+        cheat
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.cheats[index].enabled = enabled;
+        self.update_rom_patches();
+    }
+
+    pub fn clear(&mut self) {
+        self.cheats.clear();
+        self.update_rom_patches();
+    }
+
+    fn update_rom_patches(&mut self) {
+        self.rom_patches = self
+            .enabled()
+            .filter_map(|code| match code {
+                CheatCode::GameGenie {
+                    address,
+                    value,
+                    compare,
+                } => Some((address, value, compare)),
+                CheatCode::GameShark { .. } => None,
+            })
+            .collect();
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = CheatCode> + '_ {
+        self.cheats
+            .iter()
+            .filter(|cheat| cheat.enabled)
+            .map(|cheat| cheat.code)
+    }
+
+    /// Apply any Game Genie codes to a value read from cartridge ROM at the given address.
+    #[inline]
+    pub fn patch_rom(&self, addr: u16, value: u8) -> u8 {
+        if self.rom_patches.is_empty() {
+            return value;
+        }
+
+        self.rom_patches
+            .iter()
+            .find(|(address, _, compare)| {
+                *address == addr && compare.is_none_or(|compare| compare == value)
+            })
+            .map_or(value, |(_, patched, _)| *patched)
+    }
+
+    /// The writes to RAM (as cartridge RAM bank, address, and value) made by the enabled GameShark codes each frame.
+    pub fn ram_writes(&self) -> impl Iterator<Item = (Option<u8>, u16, u8)> + '_ {
+        self.enabled().filter_map(|code| match code {
+            CheatCode::GameShark {
+                bank,
+                address,
+                value,
+            } => Some((bank, address, value)),
+            CheatCode::GameGenie { .. } => None,
+        })
+    }
+}
+
+/// Parses a list of cheats with one code per line, optionally followed by a description. Blank lines and those
+/// starting with `#` are ignored.
+///
+/// ```text
+/// # comment
+/// 01FF34C2 Infinite health
+/// 00A-17B-C49 Start with 9 lives
+/// ```
+impl FromStr for Cheats {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cheats = Cheats::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (code, description) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let code = code.parse().map_err(|e: ParseError| ParseError {
+                line: index + 1,
+                ..e
+            })?;
+            cheats.add(code, description.trim());
+        }
+
+        Ok(cheats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_game_genie() {
+        assert_eq!(
+            "00A-17B-C49".parse::<CheatCode>().unwrap(),
+            CheatCode::GameGenie {
+                address: 0x4A17,
+                value: 0x00,
+                compare: Some(0xC8),
+            }
+        );
+        assert_eq!(
+            "3EF-0EF".parse::<CheatCode>().unwrap(),
+            CheatCode::GameGenie {
+                address: 0x0F0E,
+                value: 0x3E,
+                compare: None,
+            }
+        );
+        assert!("00A-170".parse::<CheatCode>().is_err()); // patches 0xFA17, outside of ROM
+    }
+
+    #[test]
+    fn parse_gameshark() {
+        assert_eq!(
+            "01FF34C2".parse::<CheatCode>().unwrap(),
+            CheatCode::GameShark {
+                bank: None,
+                address: 0xC234,
+                value: 0xFF,
+            }
+        );
+        assert_eq!(
+            "83FF34A2".parse::<CheatCode>().unwrap(),
+            CheatCode::GameShark {
+                bank: Some(3),
+                address: 0xA234,
+                value: 0xFF,
+            }
+        );
+        assert!("01FF3412".parse::<CheatCode>().is_err()); // writes to 0x1234 in ROM
+        assert!("83FF34C2".parse::<CheatCode>().is_err()); // RAM bank given for work RAM
+        assert!("91FF34D2".parse::<CheatCode>().is_err()); // CGB work RAM banks are not supported
+        assert!("01FF34CG".parse::<CheatCode>().is_err());
+        assert!("01FF34C".parse::<CheatCode>().is_err());
+    }
+
+    #[test]
+    fn rom_patches() {
+        let mut cheats = Cheats::new();
+        assert_eq!(cheats.patch_rom(0x4A17, 0xC8), 0xC8);
+
+        let index = cheats.add("00A-17B-C49".parse().unwrap(), "");
+        assert_eq!(cheats.patch_rom(0x4A17, 0xC8), 0x00);
+        assert_eq!(cheats.patch_rom(0x4A17, 0xC9), 0xC9); // compare value doesn't match
+        assert_eq!(cheats.patch_rom(0x4A18, 0xC8), 0xC8);
+
+        cheats.set_enabled(index, false);
+        assert_eq!(cheats.patch_rom(0x4A17, 0xC8), 0xC8);
+    }
+
+    #[test]
+    fn parse_list() {
+        let cheats: Cheats = "# comment\n\n01FF34C2  Infinite health\n3EF-0EF\n"
+            .parse()
+            .unwrap();
+        assert_eq!(cheats.cheats().len(), 2);
+        assert_eq!(cheats.cheats()[0].description, "Infinite health");
+        assert_eq!(
+            cheats.ram_writes().collect::<Vec<_>>(),
+            [(None, 0xC234, 0xFF)]
+        );
+
+        assert_eq!("3EF-0EF\nnonsense".parse::<Cheats>().unwrap_err().line, 2);
+    }
+}
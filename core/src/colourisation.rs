@@ -0,0 +1,317 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cartridge::Cartridge;
+use crate::palette::{rgb555_to_rgb, ColourPalette, Shades};
+
+/// ID of a palette combination (an index into [`COMBINATIONS`]).
+pub type PaletteId = u8;
+
+/// Palette combination given to games not found in the title table.
+pub const DEFAULT_PALETTE_ID: PaletteId = 0;
+
+/// The colours available to the boot ROM in RGB555 format, four per palette.
+#[rustfmt::skip]
+const COLOURS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000,
+    0x639F, 0x4279, 0x15B0, 0x04CB,
+    0x7FFF, 0x6E31, 0x454A, 0x0000,
+    0x7FFF, 0x1BEF, 0x0200, 0x0000,
+    0x7FFF, 0x421F, 0x1CF2, 0x0000,
+    0x7FFF, 0x5294, 0x294A, 0x0000,
+    0x7FFF, 0x03FF, 0x012F, 0x0000,
+    0x7FFF, 0x03EF, 0x01D6, 0x0000,
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000,
+    0x7E74, 0x03FF, 0x0180, 0x0000,
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B,
+    0x7ED6, 0x4BFF, 0x2175, 0x0000,
+    0x53FF, 0x4A5F, 0x7E52, 0x0000,
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0,
+    0x03ED, 0x7FFF, 0x255F, 0x0000,
+    0x036A, 0x021F, 0x03FF, 0x7FFF,
+    0x7FFF, 0x01DF, 0x0112, 0x0000,
+    0x231F, 0x035F, 0x00F2, 0x0009,
+    0x7FFF, 0x03EA, 0x011F, 0x0000,
+    0x299F, 0x001A, 0x000C, 0x0000,
+    0x7FFF, 0x027F, 0x001F, 0x0000,
+    0x7FFF, 0x03E0, 0x0206, 0x0120,
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00,
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+    0x7FFF, 0x03FF, 0x001F, 0x0000,
+    0x03FF, 0x001F, 0x000C, 0x0000,
+    0x7FFF, 0x033F, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037F, 0x7FFF,
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000,
+    0x7FFF, 0x1BEF, 0x6180, 0x0000,
+];
+
+/// Palette combinations given as offsets into [`COLOURS`] of the first colour of the object 0, object 1, and
+/// background palettes respectively. Offsets are usually the start of a palette but a few combinations deliberately
+/// straddle two palettes.
+#[rustfmt::skip]
+const COMBINATIONS: [[u8; 3]; 51] = [
+    combination(4, 4, 29),  combination(18, 18, 18), combination(20, 20, 20), combination(24, 24, 24),
+    combination(9, 9, 9),   combination(0, 0, 0),    combination(27, 27, 27), combination(5, 5, 5),
+    combination(12, 12, 12), combination(26, 26, 26), combination(16, 8, 8),  combination(4, 28, 28),
+    combination(4, 2, 2),   combination(3, 4, 4),    combination(4, 29, 29),  combination(28, 4, 28),
+    combination(2, 17, 2),  combination(16, 16, 8),  combination(4, 4, 7),    combination(4, 4, 18),
+    combination(4, 4, 20),  combination(19, 19, 9),  [15, 15, 44],            combination(17, 17, 2),
+    combination(4, 4, 2),   combination(4, 4, 3),    combination(28, 28, 0),  combination(3, 3, 0),
+    combination(0, 0, 1),   combination(18, 22, 18), combination(20, 22, 20), combination(24, 22, 24),
+    combination(16, 22, 8), combination(17, 4, 13),  [111, 0, 56],            [111, 16, 60],
+    combination(19, 22, 9), combination(16, 28, 10), combination(4, 23, 28),  combination(17, 22, 2),
+    combination(4, 0, 2),   combination(4, 28, 3),   combination(28, 3, 0),   combination(3, 28, 4),
+    combination(21, 28, 4), combination(3, 28, 0),   combination(25, 3, 28),  combination(0, 28, 8),
+    combination(4, 3, 28),  combination(28, 3, 6),   combination(4, 28, 29),
+];
+
+/// Palette combinations of games published by Nintendo, given as the title checksum, the 4th letter of the title
+/// (only present for games whose checksum is shared with another game), and the palette ID.
+#[rustfmt::skip]
+const TITLE_PALETTES: [(u8, Option<u8>, PaletteId); 94] = [
+    (0x00, None, 0), (0x88, None, 4), (0x16, None, 5), (0x36, None, 35), (0xD1, None, 34),
+    (0xDB, None, 3), (0xF2, None, 31), (0x3C, None, 15), (0x8C, None, 10), (0x92, None, 5),
+    (0x3D, None, 19), (0x5C, None, 36), (0x58, None, 7), (0xC9, None, 37), (0x3E, None, 30),
+    (0x70, None, 44), (0x1D, None, 21), (0x59, None, 32), (0x69, None, 31), (0x19, None, 20),
+    (0x35, None, 5), (0xA8, None, 33), (0x14, None, 13), (0xAA, None, 14), (0x75, None, 5),
+    (0x95, None, 29), (0x99, None, 5), (0x34, None, 18), (0x6F, None, 9), (0x15, None, 3),
+    (0xFF, None, 2), (0x97, None, 26), (0x4B, None, 25), (0x90, None, 25), (0x17, None, 41),
+    (0x10, None, 42), (0x39, None, 26), (0xF7, None, 45), (0xF6, None, 42), (0xA2, None, 45),
+    (0x49, None, 36), (0x4E, None, 38), (0x43, None, 26), (0x68, None, 42), (0xE0, None, 30),
+    (0x8B, None, 41), (0xF0, None, 34), (0xCE, None, 34), (0x0C, None, 5), (0x29, None, 42),
+    (0xE8, None, 6), (0xB7, None, 5), (0x86, None, 33), (0x9A, None, 25), (0x52, None, 42),
+    (0x01, None, 42), (0x9D, None, 40), (0x71, None, 2), (0x9C, None, 16), (0xBD, None, 25),
+    (0x5D, None, 42), (0x6D, None, 42), (0x67, None, 5), (0x3F, None, 0), (0x6B, None, 39),
+    (0xB3, Some(b'B'), 36), (0x46, Some(b'E'), 22), (0x28, Some(b'F'), 25), (0xA5, Some(b'A'), 6),
+    (0xC6, Some(b'A'), 32), (0xD3, Some(b'R'), 12), (0x27, Some(b'B'), 36), (0x61, Some(b'E'), 11),
+    (0x18, Some(b'K'), 39), (0x66, Some(b'E'), 18), (0x6A, Some(b'K'), 39), (0xBF, Some(b' '), 24),
+    (0x0D, Some(b'R'), 31), (0xF4, Some(b'-'), 50), (0xB3, Some(b'U'), 17), (0x46, Some(b'R'), 46),
+    (0x28, Some(b'A'), 6), (0xA5, Some(b'R'), 27), (0xC6, Some(b' '), 0), (0xD3, Some(b'I'), 47),
+    (0x27, Some(b'N'), 41), (0x61, Some(b'A'), 41), (0x18, Some(b'I'), 0), (0x66, Some(b'L'), 0),
+    (0x6A, Some(b'I'), 7), (0xBF, Some(b'C'), 39), (0x0D, Some(b'E'), 41), (0xF4, Some(b' '), 5),
+    (0xB3, Some(b'R'), 8),
+];
+
+const fn combination(obj0: u8, obj1: u8, bg: u8) -> [u8; 3] {
+    [obj0 * 4, obj1 * 4, bg * 4]
+}
+
+/// Determine the ID of the palette combination the CGB boot ROM would select for the given cartridge. Games published
+/// by Nintendo are identified by their title checksum (and, where checksums collide, the 4th letter of their title)
+/// while all other games receive the default combination.
+pub fn palette_id(cart: &Cartridge) -> PaletteId {
+    if !cart.licensed_by_nintendo() {
+        return DEFAULT_PALETTE_ID;
+    }
+
+    let fourth_letter = cart.game_title().as_bytes().get(3).copied().unwrap_or(0);
+    lookup(&TITLE_PALETTES, cart.title_checksum(), fourth_letter).unwrap_or(DEFAULT_PALETTE_ID)
+}
+
+/// Get the palette combination the CGB boot ROM would select for the given cartridge.
+pub fn colourise(cart: &Cartridge) -> ColourPalette {
+    palette(palette_id(cart)).unwrap()
+}
+
+/// Get the palette combination with the given ID, or `None` if there is no such combination.
+pub fn palette(id: PaletteId) -> Option<ColourPalette> {
+    let [obj0, obj1, bg] = COMBINATIONS.get(id as usize)?.map(shades);
+    Some(ColourPalette { bg, obj0, obj1 })
+}
+
+fn lookup(
+    table: &[(u8, Option<u8>, PaletteId)],
+    checksum: u8,
+    fourth_letter: u8,
+) -> Option<PaletteId> {
+    table
+        .iter()
+        .find(|(c, letter, _)| *c == checksum && letter.is_none_or(|l| l == fourth_letter))
+        .map(|(_, _, id)| *id)
+}
+
+fn shades(offset: u8) -> Shades {
+    std::array::from_fn(|i| rgb555_to_rgb(COLOURS[offset as usize + i]))
+}
+
+/// Button combinations that may be held while the CGB boot ROM displays the logo to select a palette manually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonCombo {
+    Up,
+    UpA,
+    UpB,
+    Down,
+    DownA,
+    DownB,
+    Left,
+    LeftA,
+    LeftB,
+    Right,
+    RightA,
+    RightB,
+}
+
+impl ButtonCombo {
+    pub const ALL: [ButtonCombo; 12] = [
+        ButtonCombo::Up,
+        ButtonCombo::UpA,
+        ButtonCombo::UpB,
+        ButtonCombo::Down,
+        ButtonCombo::DownA,
+        ButtonCombo::DownB,
+        ButtonCombo::Left,
+        ButtonCombo::LeftA,
+        ButtonCombo::LeftB,
+        ButtonCombo::Right,
+        ButtonCombo::RightA,
+        ButtonCombo::RightB,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ButtonCombo::Up => "up",
+            ButtonCombo::UpA => "up+a",
+            ButtonCombo::UpB => "up+b",
+            ButtonCombo::Down => "down",
+            ButtonCombo::DownA => "down+a",
+            ButtonCombo::DownB => "down+b",
+            ButtonCombo::Left => "left",
+            ButtonCombo::LeftA => "left+a",
+            ButtonCombo::LeftB => "left+b",
+            ButtonCombo::Right => "right",
+            ButtonCombo::RightA => "right+a",
+            ButtonCombo::RightB => "right+b",
+        }
+    }
+
+    /// ID of the palette combination selected by this button combination.
+    pub fn palette_id(&self) -> PaletteId {
+        match self {
+            ButtonCombo::Up => 5,
+            ButtonCombo::UpA => 43,
+            ButtonCombo::UpB => 28,
+            ButtonCombo::Down => 8,
+            ButtonCombo::DownA => 3,
+            ButtonCombo::DownB => 49,
+            ButtonCombo::Left => 48,
+            ButtonCombo::LeftA => 40,
+            ButtonCombo::LeftB => 7,
+            ButtonCombo::Right => 1,
+            ButtonCombo::RightA => 0,
+            ButtonCombo::RightB => 6,
+        }
+    }
+
+    pub fn palette(&self) -> ColourPalette {
+        palette(self.palette_id()).unwrap()
+    }
+}
+
+impl fmt::Display for ButtonCombo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ButtonCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ButtonCombo::ALL
+            .into_iter()
+            .find(|c| c.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = ButtonCombo::ALL.iter().map(ButtonCombo::name).collect();
+                format!(
+                    "unknown button combination '{s}' (expected one of: {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cartridge(title: &str, old_licensee: u8) -> Cartridge {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title.as_bytes());
+        rom[0x14B] = old_licensee;
+        Cartridge::from_data(rom)
+    }
+
+    #[test]
+    fn button_combos() {
+        let p = ButtonCombo::RightA.palette();
+        assert_eq!(
+            p.bg,
+            [
+                [0xFF, 0xFF, 0xFF],
+                [0x7B, 0xFF, 0x31],
+                [0x00, 0x63, 0xC5],
+                [0, 0, 0]
+            ]
+        );
+        assert_eq!(
+            p.obj0,
+            [
+                [0xFF, 0xFF, 0xFF],
+                [0xFF, 0x84, 0x84],
+                [0x94, 0x3A, 0x3A],
+                [0, 0, 0]
+            ]
+        );
+        assert_eq!(p.obj1, p.obj0);
+
+        let p = ButtonCombo::LeftA.palette();
+        assert_eq!(p.bg[1], [0x8C, 0x8C, 0xDE]);
+        assert_eq!(p.obj0[1], [0xFF, 0x84, 0x84]);
+        assert_eq!(p.obj1[1], [0xFF, 0xAD, 0x63]);
+
+        assert_eq!(ButtonCombo::RightB.palette().bg[3], [0xFF, 0xFF, 0xFF]);
+        assert_eq!("down+b".parse::<ButtonCombo>(), Ok(ButtonCombo::DownB));
+        assert!("a+b".parse::<ButtonCombo>().is_err());
+    }
+
+    #[test]
+    fn title_lookup() {
+        let tetris = cartridge("TETRIS", 0x01);
+        assert_eq!(tetris.title_checksum(), 0xDB);
+        assert_eq!(palette_id(&tetris), 3);
+        assert_eq!(colourise(&tetris), ButtonCombo::DownA.palette());
+
+        // only games published by Nintendo are looked up
+        assert_eq!(palette_id(&cartridge("TETRIS", 0x00)), DEFAULT_PALETTE_ID);
+
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x13A].copy_from_slice(b"TETRIS");
+        rom[0x144..0x146].copy_from_slice(b"01");
+        rom[0x14B] = 0x33;
+        assert_eq!(palette_id(&Cartridge::from_data(rom)), 3);
+
+        assert_eq!(palette_id(&cartridge("UNKNOWN", 0x01)), DEFAULT_PALETTE_ID);
+    }
+
+    #[test]
+    fn fourth_letter() {
+        // these pairs of games share a title checksum so are told apart by the 4th letter of their titles
+        let mario = cartridge("SUPER MARIOLAND", 0x01);
+        let metroid = cartridge("METROID2", 0x01);
+        assert_eq!(mario.title_checksum(), metroid.title_checksum());
+        assert_eq!(palette_id(&mario), 22);
+        assert_eq!(palette_id(&metroid), 46);
+
+        assert_eq!(palette_id(&cartridge("POKEMON BLUE", 0x01)), 11);
+        assert_eq!(palette_id(&cartridge("VEGAS STAKES", 0x01)), 41);
+
+        // a shared checksum with an unknown 4th letter is given the default palette
+        assert_eq!(palette_id(&cartridge("METZOID*", 0x01)), DEFAULT_PALETTE_ID);
+    }
+
+    #[test]
+    fn all_combinations_in_bounds() {
+        assert!((0..COMBINATIONS.len() as PaletteId).all(|id| palette(id).is_some()));
+        assert!(palette(COMBINATIONS.len() as PaletteId).is_none());
+    }
+}
@@ -0,0 +1,366 @@
+use std::fmt;
+
+use super::opcode::Opcode;
+use crate::bus::MemoryBus;
+use crate::Cycles;
+
+/// The longest instruction is 3 bytes (an opcode followed by a 16-bit operand).
+pub const MAX_INSTRUCTION_LENGTH: usize = 3;
+
+/// Size of each cartridge ROM bank.
+const ROM_BANK_SIZE: usize = 0x4000;
+
+const R8: [&str; 8] = ["B", "C", "D", "E", "H", "L", "[HL]", "A"];
+/// Index of [HL] among the 8-bit operands.
+const HL_INDIRECT: u8 = 6;
+const R16: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const R16_STACK: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const R16_MEMORY: [&str; 4] = ["[BC]", "[DE]", "[HL+]", "[HL-]"];
+const CONDITIONS: [&str; 4] = ["NZ", "Z", "NC", "C"];
+const ALU: [&str; 8] = ["ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP"];
+const ACCUMULATOR_OPS: [&str; 8] = ["RLCA", "RRCA", "RLA", "RRA", "DAA", "CPL", "SCF", "CCF"];
+const SHIFTS: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SWAP", "SRL"];
+
+/// A single decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    /// The bytes making up the instruction (the opcode followed by any operands).
+    pub bytes: Vec<u8>,
+    /// The instruction in assembly (e.g., `LD A, [$C000]`) with numbers written in hexadecimal.
+    pub text: String,
+    /// Number of T-cycles taken to execute the instruction (should it be a conditional jump, call, or return, the
+    /// number taken when the condition is false).
+    pub cycles: Cycles,
+    /// Number of T-cycles taken by a conditional jump, call, or return when the condition is true.
+    pub branch_cycles: Option<Cycles>,
+    /// The address that the instruction jumps to or accesses, if it is known without executing it (e.g., the
+    /// destination of `JP $0150` or the register accessed by `LDH A, [$FF44]`).
+    pub target: Option<u16>,
+}
+
+impl Instruction {
+    /// Number of bytes making up the instruction.
+    pub fn length(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+
+    /// The address of the instruction following this one.
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.length())
+    }
+
+    /// Replace the target address in the text of the instruction with a label (e.g., `CALL $0150` becomes
+    /// `CALL Main`). Does nothing should the instruction have no target.
+    pub fn label_target(&mut self, label: &str) {
+        if self.target.is_none() {
+            return;
+        }
+        if let Some(start) = self.text.rfind('$') {
+            let end = self.text[start + 1..]
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .map_or(self.text.len(), |len| start + 1 + len);
+            self.text.replace_range(start..end, label);
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Disassemble the instruction at the given address in memory as it is currently mapped (i.e., using whichever ROM
+/// bank is currently selected). The target of the instruction is replaced with its label should there be one in
+/// [`MemoryBus::symbols`].
+pub fn disassemble(bus: &MemoryBus, address: u16) -> Instruction {
+    let mut instr = decode(
+        std::array::from_fn(|i| bus.read8(address.wrapping_add(i as u16))),
+        address,
+    );
+    if let Some(label) = instr
+        .target
+        .and_then(|target| bus.symbols.label(target, bus.rom_bank(target)))
+    {
+        instr.label_target(label);
+    }
+    instr
+}
+
+/// Disassemble the instruction at the given address (from 0x0000 to 0x7FFF) within the given bank of raw ROM data.
+/// Bytes beyond the end of the ROM are treated as 0.
+pub fn disassemble_rom(rom: &[u8], bank: u16, address: u16) -> Instruction {
+    let offset = bank as usize * ROM_BANK_SIZE + (address as usize % ROM_BANK_SIZE);
+    decode(
+        std::array::from_fn(|i| rom.get(offset + i).copied().unwrap_or(0)),
+        address,
+    )
+}
+
+/// Decode the instruction made up of (some prefix of) the given bytes, located at the given address. Illegal opcodes
+/// are decoded as a single byte of data (e.g., `DB $D3`).
+pub fn decode(bytes: [u8; MAX_INSTRUCTION_LENGTH], address: u16) -> Instruction {
+    let opcode = Opcode(bytes[0]);
+    let n8 = bytes[1];
+    let n16 = u16::from_le_bytes([bytes[1], bytes[2]]);
+    let e8 = n8 as i8;
+    let relative = address.wrapping_add(2).wrapping_add(e8 as u16);
+
+    let r8 = |index: u8| R8[index as usize];
+    let rr = opcode.rr() as usize;
+    let cc = CONDITIONS[opcode.ff() as usize];
+    // instructions taking an 8-bit register take longer when that register is [HL] as memory must be accessed
+    let hl_cycles =
+        |index: u8, cycles: Cycles, hl: Cycles| if index == HL_INDIRECT { hl } else { cycles };
+
+    let decoded = match opcode.0 {
+        0x00 => op("NOP", 1, 4),
+        0x01 | 0x11 | 0x21 | 0x31 => op(format!("LD {}, ${n16:04X}", R16[rr]), 3, 12),
+        0x02 | 0x12 | 0x22 | 0x32 => op(format!("LD {}, A", R16_MEMORY[rr]), 1, 8),
+        0x0A | 0x1A | 0x2A | 0x3A => op(format!("LD A, {}", R16_MEMORY[rr]), 1, 8),
+        0x03 | 0x13 | 0x23 | 0x33 => op(format!("INC {}", R16[rr]), 1, 8),
+        0x0B | 0x1B | 0x2B | 0x3B => op(format!("DEC {}", R16[rr]), 1, 8),
+        0x09 | 0x19 | 0x29 | 0x39 => op(format!("ADD HL, {}", R16[rr]), 1, 8),
+        0x04 | 0x0C | 0x14 | 0x1C | 0x24 | 0x2C | 0x34 | 0x3C => op(
+            format!("INC {}", r8(opcode.xxx())),
+            1,
+            hl_cycles(opcode.xxx(), 4, 12),
+        ),
+        0x05 | 0x0D | 0x15 | 0x1D | 0x25 | 0x2D | 0x35 | 0x3D => op(
+            format!("DEC {}", r8(opcode.xxx())),
+            1,
+            hl_cycles(opcode.xxx(), 4, 12),
+        ),
+        0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => op(
+            format!("LD {}, ${n8:02X}", r8(opcode.xxx())),
+            2,
+            hl_cycles(opcode.xxx(), 8, 12),
+        ),
+        0x07 | 0x0F | 0x17 | 0x1F | 0x27 | 0x2F | 0x37 | 0x3F => {
+            op(ACCUMULATOR_OPS[opcode.xxx() as usize], 1, 4)
+        }
+        0x08 => op(format!("LD [${n16:04X}], SP"), 3, 20).target(n16),
+        0x10 => op("STOP", 2, 4),
+        0x18 => op(format!("JR ${relative:04X}"), 2, 12).target(relative),
+        0x20 | 0x28 | 0x30 | 0x38 => op(format!("JR {cc}, ${relative:04X}"), 2, 8)
+            .branch(12)
+            .target(relative),
+
+        0x76 => op("HALT", 1, 4),
+        0x40..=0x7F => {
+            let cycles = if opcode.xxx() == HL_INDIRECT || opcode.yyy() == HL_INDIRECT {
+                8
+            } else {
+                4
+            };
+            op(
+                format!("LD {}, {}", r8(opcode.xxx()), r8(opcode.yyy())),
+                1,
+                cycles,
+            )
+        }
+
+        0x80..=0xBF => op(
+            format!("{} A, {}", ALU[opcode.xxx() as usize], r8(opcode.yyy())),
+            1,
+            hl_cycles(opcode.yyy(), 4, 8),
+        ),
+
+        0xC0 | 0xC8 | 0xD0 | 0xD8 => op(format!("RET {cc}"), 1, 8).branch(20),
+        0xC9 => op("RET", 1, 16),
+        0xD9 => op("RETI", 1, 16),
+        0xC1 | 0xD1 | 0xE1 | 0xF1 => op(format!("POP {}", R16_STACK[rr]), 1, 12),
+        0xC5 | 0xD5 | 0xE5 | 0xF5 => op(format!("PUSH {}", R16_STACK[rr]), 1, 16),
+        0xC2 | 0xCA | 0xD2 | 0xDA => op(format!("JP {cc}, ${n16:04X}"), 3, 12)
+            .branch(16)
+            .target(n16),
+        0xC3 => op(format!("JP ${n16:04X}"), 3, 16).target(n16),
+        0xE9 => op("JP HL", 1, 4),
+        0xC4 | 0xCC | 0xD4 | 0xDC => op(format!("CALL {cc}, ${n16:04X}"), 3, 12)
+            .branch(24)
+            .target(n16),
+        0xCD => op(format!("CALL ${n16:04X}"), 3, 24).target(n16),
+        0xC6 | 0xCE | 0xD6 | 0xDE | 0xE6 | 0xEE | 0xF6 | 0xFE => {
+            op(format!("{} A, ${n8:02X}", ALU[opcode.xxx() as usize]), 2, 8)
+        }
+        0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => {
+            let vector = (opcode.0 & 0x38) as u16;
+            op(format!("RST ${vector:02X}"), 1, 16).target(vector)
+        }
+        0xCB => decode_cb(Opcode(n8)),
+
+        0xE0 => op(format!("LDH [$FF{n8:02X}], A"), 2, 12).target(0xFF00 | n8 as u16),
+        0xF0 => op(format!("LDH A, [$FF{n8:02X}]"), 2, 12).target(0xFF00 | n8 as u16),
+        0xE2 => op("LDH [C], A", 1, 8),
+        0xF2 => op("LDH A, [C]", 1, 8),
+        0xEA => op(format!("LD [${n16:04X}], A"), 3, 16).target(n16),
+        0xFA => op(format!("LD A, [${n16:04X}]"), 3, 16).target(n16),
+        0xE8 => op(format!("ADD SP, {e8}"), 2, 16),
+        0xF8 => op(format!("LD HL, SP{e8:+}"), 2, 12),
+        0xF9 => op("LD SP, HL", 1, 8),
+        0xF3 => op("DI", 1, 4),
+        0xFB => op("EI", 1, 4),
+
+        // 0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD
+        illegal => op(format!("DB ${illegal:02X}"), 1, 4),
+    };
+
+    Instruction {
+        address,
+        bytes: bytes[..decoded.length].to_vec(),
+        text: decoded.text,
+        cycles: decoded.cycles,
+        branch_cycles: decoded.branch_cycles,
+        target: decoded.target,
+    }
+}
+
+/// Decode the second byte of a 0xCB prefixed instruction.
+fn decode_cb(opcode: Opcode) -> Decoded {
+    let register = opcode.yyy();
+    let bit = opcode.xxx();
+
+    let (text, hl_cycles) = match opcode.0 >> 6 {
+        0 => (
+            format!("{} {}", SHIFTS[bit as usize], R8[register as usize]),
+            16,
+        ),
+        1 => (format!("BIT {bit}, {}", R8[register as usize]), 12),
+        2 => (format!("RES {bit}, {}", R8[register as usize]), 16),
+        _ => (format!("SET {bit}, {}", R8[register as usize]), 16),
+    };
+
+    op(
+        text,
+        2,
+        if register == HL_INDIRECT {
+            hl_cycles
+        } else {
+            8
+        },
+    )
+}
+
+struct Decoded {
+    text: String,
+    length: usize,
+    cycles: Cycles,
+    branch_cycles: Option<Cycles>,
+    target: Option<u16>,
+}
+
+fn op(text: impl Into<String>, length: usize, cycles: Cycles) -> Decoded {
+    Decoded {
+        text: text.into(),
+        length,
+        cycles,
+        branch_cycles: None,
+        target: None,
+    }
+}
+
+impl Decoded {
+    fn branch(self, cycles: Cycles) -> Self {
+        Decoded {
+            branch_cycles: Some(cycles),
+            ..self
+        }
+    }
+
+    fn target(self, target: u16) -> Self {
+        Decoded {
+            target: Some(target),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(bytes: &[u8]) -> String {
+        let mut padded = [0; MAX_INSTRUCTION_LENGTH];
+        padded[..bytes.len()].copy_from_slice(bytes);
+        let instruction = decode(padded, 0x0150);
+        assert_eq!(instruction.bytes, bytes);
+        instruction.text
+    }
+
+    #[test]
+    fn mnemonics() {
+        assert_eq!(text(&[0x00]), "NOP");
+        assert_eq!(text(&[0x01, 0x34, 0x12]), "LD BC, $1234");
+        assert_eq!(text(&[0x2A]), "LD A, [HL+]");
+        assert_eq!(text(&[0x36, 0x42]), "LD [HL], $42");
+        assert_eq!(text(&[0x78]), "LD A, B");
+        assert_eq!(text(&[0x9E]), "SBC A, [HL]");
+        assert_eq!(text(&[0xFE, 0x90]), "CP A, $90");
+        assert_eq!(text(&[0xF1]), "POP AF");
+        assert_eq!(text(&[0xE0, 0x40]), "LDH [$FF40], A");
+        assert_eq!(text(&[0xE8, 0xFE]), "ADD SP, -2");
+        assert_eq!(text(&[0xF8, 0x05]), "LD HL, SP+5");
+        assert_eq!(text(&[0xEF]), "RST $28");
+        assert_eq!(text(&[0xD3]), "DB $D3");
+    }
+
+    #[test]
+    fn cb_prefixed() {
+        assert_eq!(text(&[0xCB, 0x37]), "SWAP A");
+        assert_eq!(text(&[0xCB, 0x7E]), "BIT 7, [HL]");
+        assert_eq!(text(&[0xCB, 0x80]), "RES 0, B");
+        assert_eq!(text(&[0xCB, 0xFF]), "SET 7, A");
+    }
+
+    #[test]
+    fn branches() {
+        let jr = decode([0x20, 0xFE, 0x00], 0x0150);
+        assert_eq!(jr.text, "JR NZ, $0150");
+        assert_eq!((jr.cycles, jr.branch_cycles), (8, Some(12)));
+        assert_eq!(jr.target, Some(0x0150));
+        assert_eq!(jr.next_address(), 0x0152);
+
+        let call = decode([0xCD, 0x00, 0x40], 0x0150);
+        assert_eq!(call.text, "CALL $4000");
+        assert_eq!((call.cycles, call.branch_cycles), (24, None));
+        assert_eq!(call.length(), 3);
+    }
+
+    #[test]
+    fn labels() {
+        let mut call = decode([0xCD, 0x00, 0x40], 0x0150);
+        call.label_target("LoadTiles");
+        assert_eq!(call.text, "CALL LoadTiles");
+
+        let mut ld = decode([0xEA, 0x00, 0xC0], 0);
+        ld.label_target("wBuffer");
+        assert_eq!(ld.text, "LD [wBuffer], A");
+
+        let mut nop = decode([0x00, 0x00, 0x00], 0);
+        nop.label_target("Nothing");
+        assert_eq!(nop.text, "NOP");
+    }
+
+    #[test]
+    fn cycles() {
+        let cycles = |bytes: [u8; 3]| decode(bytes, 0).cycles;
+        assert_eq!(cycles([0x34, 0, 0]), 12); // INC [HL]
+        assert_eq!(cycles([0x77, 0, 0]), 8); // LD [HL], A
+        assert_eq!(cycles([0x08, 0, 0]), 20); // LD [a16], SP
+        assert_eq!(cycles([0xCB, 0x46, 0]), 12); // BIT 0, [HL]
+        assert_eq!(cycles([0xCB, 0x06, 0]), 16); // RLC [HL]
+    }
+
+    #[test]
+    fn rom_banks() {
+        let mut rom = vec![0; 0x10000];
+        rom[0x8123..0x8126].copy_from_slice(&[0xC3, 0x50, 0x01]);
+        let instruction = disassemble_rom(&rom, 2, 0x4123);
+        assert_eq!(instruction.address, 0x4123);
+        assert_eq!(instruction.text, "JP $0150");
+
+        // beyond the end of the ROM
+        assert_eq!(disassemble_rom(&rom, 8, 0x4000).text, "NOP");
+    }
+}
@@ -1,20 +1,52 @@
 mod alu;
+pub mod disassembler;
 mod ime;
 mod opcode;
 mod registers;
 
 use crate::bits::modify_bit;
 use crate::bus::MemoryBus;
-use crate::Cycles;
+use crate::debugger::call_stack::{CallStack, Frame, FrameKind};
+use crate::debugger::Access;
+use crate::gpu::oam::OamCorruption;
+use crate::{Cycles, M_CYCLE};
 
 use ime::InterruptMasterEnable;
 use opcode::Opcode;
 use registers::{Flags, Registers};
 
+/// Number of M-cycles for which the CPU is paused after a CGB speed switch while the clock stabilises.
+const SPEED_SWITCH_PAUSE: u32 = 2050;
+
+/// The execution state of the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Fetching and executing instructions as normal.
+    Running,
+    /// Entered by the HALT instruction. No instructions are executed until an interrupt is pending.
+    Halted,
+    /// Entered by the STOP instruction. The system clock is stopped until one of the joypad input lines goes low.
+    Stopped,
+    /// Entered when an illegal opcode is executed. The CPU hangs - no further instructions are executed and interrupts
+    /// are ignored - until the system is reset.
+    Locked {
+        /// Address from which the illegal opcode was fetched.
+        address: u16,
+        opcode: u8,
+    },
+}
+
 pub struct Cpu {
     pub regs: Registers,
-    halted: bool,
+    state: State,
     ime: InterruptMasterEnable,
+    /// Set when HALT is executed with IME=0 and an interrupt pending. The CPU then fails to increment PC after fetching
+    /// the next opcode, causing the byte following HALT to be read twice.
+    halt_bug: bool,
+    /// Number of cycles elapsed so far during the current call to [`Cpu::cycle`].
+    cycles: Cycles,
+    /// Calls made and interrupt handlers dispatched that have yet to return.
+    call_stack: CallStack,
 }
 
 impl Cpu {
@@ -32,77 +64,178 @@ impl Cpu {
                 sp: 0xFFFE,
                 pc: 0x0100,
             },
-            halted: false,
+            state: State::Running,
             ime: InterruptMasterEnable::new(true),
+            halt_bug: false,
+            cycles: 0,
+            call_stack: CallStack::new(),
         }
     }
 
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// The shadow call stack tracked as CALL and RST instructions are executed and interrupts dispatched (and popped as
+    /// RET and RETI are executed), for use when debugging.
+    pub fn call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
+
+    /// Fetch and execute a single instruction (or handle an interrupt, or wait while halted/stopped) and return the
+    /// number of cycles that took. The other components of the system are updated via [`MemoryBus::update`] as each
+    /// M-cycle of the instruction occurs so that memory accesses happen at the correct time relative to them.
     pub fn cycle(&mut self, bus: &mut MemoryBus) -> Cycles {
         log::trace!("begin cycle - {}, {}", self.regs, self.ime);
 
-        let interrupt_cycles = self.handle_interrupts(bus);
+        self.cycles = 0;
 
-        let cycles = if interrupt_cycles > 0 {
-            interrupt_cycles
-        } else if self.halted {
-            4 // NOP
-        } else {
-            self.fetch_execute(bus)
-        };
+        if matches!(self.state, State::Locked { .. }) {
+            self.tick(bus);
+            return self.cycles;
+        }
+
+        if self.state == State::Stopped {
+            if bus.joypad.input_lines() == 0xF {
+                self.tick(bus);
+                return self.cycles;
+            }
+
+            log::trace!("no longer stopped due to joypad input");
+            self.state = State::Running;
+            bus.resume();
+        }
+
+        if !self.handle_interrupts(bus) {
+            if self.state == State::Halted {
+                self.tick(bus); // NOP
+            } else {
+                self.fetch_execute(bus);
+            }
+        }
 
         self.ime.cycle();
 
-        log::trace!("end cycle after {} - {}, {}", cycles, self.regs, self.ime);
+        log::trace!(
+            "end cycle after {} - {}, {}",
+            self.cycles,
+            self.regs,
+            self.ime
+        );
 
-        cycles
+        self.cycles
     }
 
-    fn handle_interrupts(&mut self, bus: &mut MemoryBus) -> Cycles {
-        let mut cycles = 0;
+    /// Handle any triggered interrupts, returning true if an interrupt was dispatched or the CPU was woken from HALT.
+    fn handle_interrupts(&mut self, bus: &mut MemoryBus) -> bool {
+        let Some(int) = bus.interrupts.next_triggered_interrupt() else {
+            return false;
+        };
 
-        if let Some(int) = bus.interrupts.next_triggered_interrupt() {
-            if self.ime.enabled() {
-                log::debug!("{int} triggered and calling handler");
+        if !self.ime.enabled() && self.state != State::Halted {
+            return false;
+        }
 
-                bus.interrupts.flag(int, false);
-                self.ime.disable(0);
+        // waking from HALT takes an additional M-cycle
+        if self.state == State::Halted {
+            log::trace!("no longer halted due to interrupt being triggered");
+            self.state = State::Running;
+            self.tick(bus);
+        }
 
-                self.stack_push(bus, self.regs.pc);
-                self.regs.pc = int.handler_address();
+        // if IME=0 and halted, execution simply continues after the HALT instruction without calling any handler
+        if self.ime.enabled() {
+            log::debug!("{int} triggered and calling handler");
+            self.dispatch_interrupt(bus);
+        }
 
-                cycles += 16;
-            } else if self.halted {
-                // if IME=0 and halted, PC is incremented so as to continue execution after the HALT instruction
-                self.regs.pc += 1;
-            }
+        true
+    }
+
+    /// Push PC to the stack and jump to the handler of the highest-priority triggered interrupt. This takes 5 M-cycles.
+    /// Which interrupt is handled is only decided after the upper byte of PC is pushed - should that push overwrite the
+    /// IE register (i.e., SP=0x0000) such that no interrupt is triggered anymore, the dispatch is cancelled and PC is
+    /// set to 0x0000.
+    fn dispatch_interrupt(&mut self, bus: &mut MemoryBus) {
+        self.ime.disable(0);
+
+        // when dispatching straight after a HALT bug, the address of the HALT instruction itself is pushed
+        let return_address = if self.halt_bug {
+            self.halt_bug = false;
+            self.regs.pc.wrapping_sub(1)
+        } else {
+            self.regs.pc
+        };
+        let [msb, lsb] = return_address.to_be_bytes();
+
+        self.tick(bus);
+        self.tick_with_address(bus, self.regs.sp);
+
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write8(bus, self.regs.sp, msb);
 
-            if self.halted {
-                log::trace!("no longer halted due to interrupt being triggered");
-                self.halted = false;
-                cycles += 4;
+        let int = bus.interrupts.next_triggered_interrupt();
+
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write8(bus, self.regs.sp, lsb);
+
+        self.regs.pc = match int {
+            Some(int) => {
+                bus.interrupts.flag(int, false);
+                bus.debugger.interrupt_dispatched(int);
+
+                let target = int.handler_address();
+                self.call_stack.push(Frame {
+                    kind: FrameKind::Interrupt(int),
+                    target,
+                    target_bank: bus.rom_bank(target),
+                    return_address,
+                    return_bank: bus.rom_bank(return_address),
+                    sp: self.regs.sp,
+                });
+                target
+            }
+            None => {
+                log::debug!("interrupt dispatch cancelled by stack push to IE register");
+                0x0000
             }
-        }
+        };
 
-        cycles
+        self.tick(bus);
     }
 
-    fn fetch_execute(&mut self, bus: &mut MemoryBus) -> Cycles {
-        let opcode = Opcode(self.fetch8(bus));
+    fn fetch_execute(&mut self, bus: &mut MemoryBus) {
+        let opcode = Opcode(self.read8_instruction(bus, self.regs.pc));
+
+        if log::log_enabled!(log::Level::Debug) {
+            let pc = self.regs.pc;
+            if let Some(label) = bus.symbols.label(pc, bus.rom_bank(pc)) {
+                log::debug!("reached label {label}");
+            }
+        }
 
         log::debug!(
             "fetched opcode {} from address {:#04X}",
             opcode,
-            self.regs.pc - 1
+            self.regs.pc
         );
 
-        let cycles = self.execute(opcode, bus);
+        if self.halt_bug {
+            log::trace!("PC not incremented due to HALT bug");
+            self.halt_bug = false;
+        } else {
+            self.regs.pc += 1;
+        }
 
-        log::trace!("executed instruction with opcode {opcode} in {cycles}");
+        self.execute(opcode, bus);
 
-        cycles
+        log::trace!(
+            "executed instruction with opcode {opcode} in {}",
+            self.cycles
+        );
     }
 
-    fn execute(&mut self, opcode: Opcode, bus: &mut MemoryBus) -> Cycles {
+    fn execute(&mut self, opcode: Opcode, bus: &mut MemoryBus) {
         match opcode.0 {
             // --- 8-BIT LOAD INSTRUCTIONS ---
 
@@ -118,7 +251,6 @@ impl Cpu {
             | 0x78..=0x7D
             | 0x7F => {
                 self.regs.set8(opcode.xxx(), self.regs.get8(opcode.yyy()));
-                4
             }
 
             // LD r, n
@@ -126,121 +258,105 @@ impl Cpu {
             0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x3E => {
                 let n = self.fetch8(bus);
                 self.regs.set8(opcode.xxx(), n);
-                8
             }
 
             // LD r, [HL]
             // 0b01xxx110
             0x46 | 0x4E | 0x56 | 0x5E | 0x66 | 0x6E | 0x7E => {
-                self.regs.set8(opcode.xxx(), bus.read8(self.regs.hl()));
-                8
+                let value = self.read8(bus, self.regs.hl());
+                self.regs.set8(opcode.xxx(), value);
             }
 
             // LD [HL], r
             // 0b01110yyy
             0x70..=0x75 | 0x77 => {
-                bus.write8(self.regs.hl(), self.regs.get8(opcode.yyy()));
-                8
+                self.write8(bus, self.regs.hl(), self.regs.get8(opcode.yyy()));
             }
 
             // LD [HL], n
             0x36 => {
-                bus.write8(self.regs.hl(), self.fetch8(bus));
-                12
+                let n = self.fetch8(bus);
+                self.write8(bus, self.regs.hl(), n);
             }
 
             // LD A, [BC]
             0x0A => {
-                self.regs.a = bus.read8(self.regs.bc());
-                8
+                self.regs.a = self.read8(bus, self.regs.bc());
             }
 
             // LD A, [DE]
             0x1A => {
-                self.regs.a = bus.read8(self.regs.de());
-                8
+                self.regs.a = self.read8(bus, self.regs.de());
             }
 
             // LD [BC], A
             0x02 => {
-                bus.write8(self.regs.bc(), self.regs.a);
-                8
+                self.write8(bus, self.regs.bc(), self.regs.a);
             }
 
             // LD [DE], A
             0x12 => {
-                bus.write8(self.regs.de(), self.regs.a);
-                8
+                self.write8(bus, self.regs.de(), self.regs.a);
             }
 
             // LD A, [nn]
             0xFA => {
                 let nn = self.fetch16(bus);
-                self.regs.a = bus.read8(nn);
-                16
+                self.regs.a = self.read8(bus, nn);
             }
 
             // LD [nn], A
             0xEA => {
                 let nn = self.fetch16(bus);
-                bus.write8(nn, self.regs.a);
-                16
+                self.write8(bus, nn, self.regs.a);
             }
 
             // LDH A, [C]
             0xF2 => {
                 let addr = 0xFF00 + (self.regs.c as u16);
-                self.regs.a = bus.read8(addr);
-                8
+                self.regs.a = self.read8(bus, addr);
             }
 
             // LDH [C], A
             0xE2 => {
                 let addr = 0xFF00 + (self.regs.c as u16);
-                bus.write8(addr, self.regs.a);
-                8
+                self.write8(bus, addr, self.regs.a);
             }
 
             // LDH A, [n]
             0xF0 => {
                 let addr = 0xFF00 + (self.fetch8(bus) as u16);
-                self.regs.a = bus.read8(addr);
-                12
+                self.regs.a = self.read8(bus, addr);
             }
 
             // LDH [n], A
             0xE0 => {
                 let addr = 0xFF00 + (self.fetch8(bus) as u16);
-                bus.write8(addr, self.regs.a);
-                12
+                self.write8(bus, addr, self.regs.a);
             }
 
             // LD A, [HL-]
             0x3A => {
-                self.regs.a = bus.read8(self.regs.hl());
+                self.regs.a = self.read8_increase(bus, self.regs.hl());
                 self.regs.set_hl(self.regs.hl() - 1);
-                8
             }
 
             // LD [HL-], A
             0x32 => {
-                bus.write8(self.regs.hl(), self.regs.a);
+                self.write8(bus, self.regs.hl(), self.regs.a);
                 self.regs.set_hl(self.regs.hl() - 1);
-                8
             }
 
             // LD A, [HL+]
             0x2A => {
-                self.regs.a = bus.read8(self.regs.hl());
+                self.regs.a = self.read8_increase(bus, self.regs.hl());
                 self.regs.set_hl(self.regs.hl() + 1);
-                8
             }
 
             // LD [HL+], A
             0x22 => {
-                bus.write8(self.regs.hl(), self.regs.a);
+                self.write8(bus, self.regs.hl(), self.regs.a);
                 self.regs.set_hl(self.regs.hl() + 1);
-                8
             }
 
             // --- 16-BIT LOAD INSTRUCTIONS ---
@@ -250,13 +366,14 @@ impl Cpu {
             0x01 | 0x11 | 0x21 | 0x31 => {
                 let nn = self.fetch16(bus);
                 self.regs.set16_with_sp(opcode.rr(), nn);
-                12
             }
 
             // LD [nn], SP
             0x08 => {
-                bus.write16(self.fetch16(bus), self.regs.sp);
-                20
+                let nn = self.fetch16(bus);
+                let [msb, lsb] = self.regs.sp.to_be_bytes();
+                self.write8(bus, nn, lsb);
+                self.write8(bus, nn.wrapping_add(1), msb);
             }
 
             // LD HL, SP+n
@@ -265,20 +382,20 @@ impl Cpu {
                 let result =
                     alu::add16_with_signed_byte_operand(&mut self.regs.flags, self.regs.sp, n);
                 self.regs.set_hl(result);
-                12
+                self.tick(bus);
             }
 
             // LD SP, HL
             0xF9 => {
                 self.regs.sp = self.regs.hl();
-                8
+                self.tick(bus);
             }
 
             // PUSH rr
             // 0b11xx0101
             0xC5 | 0xD5 | 0xE5 | 0xF5 => {
-                self.stack_push(bus, self.regs.get16_with_af(opcode.rr()));
-                16
+                let value = self.regs.get16_with_af(opcode.rr());
+                self.stack_push(bus, value);
             }
 
             // POP rr
@@ -286,7 +403,6 @@ impl Cpu {
             0xC1 | 0xD1 | 0xE1 | 0xF1 => {
                 let value = self.stack_pop(bus);
                 self.regs.set16_with_af(opcode.rr(), value);
-                12
             }
 
             // --- 8-BIT ARITHMETIC/LOGIC INSTRUCTIONS ---
@@ -296,21 +412,18 @@ impl Cpu {
             0x80..=0x85 | 0x87 => {
                 let r = self.regs.get8(opcode.yyy());
                 self.regs.a = alu::add8(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // ADD [HL]
             0x86 => {
-                let x = bus.read8(self.regs.hl());
+                let x = self.read8(bus, self.regs.hl());
                 self.regs.a = alu::add8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // ADD n
             0xC6 => {
                 let x = self.fetch8(bus);
                 self.regs.a = alu::add8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // ADC r
@@ -318,21 +431,18 @@ impl Cpu {
             0x88..=0x8D | 0x8F => {
                 let r = self.regs.get8(opcode.yyy());
                 self.regs.a = alu::adc8(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // ADC [HL]
             0x8E => {
-                let x = bus.read8(self.regs.hl());
+                let x = self.read8(bus, self.regs.hl());
                 self.regs.a = alu::adc8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // ADC n
             0xCE => {
                 let x = self.fetch8(bus);
                 self.regs.a = alu::adc8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // SUB r
@@ -340,21 +450,18 @@ impl Cpu {
             0x90..=0x95 | 0x97 => {
                 let r = self.regs.get8(opcode.yyy());
                 self.regs.a = alu::sub8(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // SUB [HL]
             0x96 => {
-                let x = bus.read8(self.regs.hl());
+                let x = self.read8(bus, self.regs.hl());
                 self.regs.a = alu::sub8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // SUB n
             0xD6 => {
                 let x = self.fetch8(bus);
                 self.regs.a = alu::sub8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // SBC r
@@ -362,21 +469,18 @@ impl Cpu {
             0x98..=0x9D | 0x9F => {
                 let r = self.regs.get8(opcode.yyy());
                 self.regs.a = alu::sbc8(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // SBC [HL]
             0x9E => {
-                let x = bus.read8(self.regs.hl());
+                let x = self.read8(bus, self.regs.hl());
                 self.regs.a = alu::sbc8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // SBC n
             0xDE => {
                 let x = self.fetch8(bus);
                 self.regs.a = alu::sbc8(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // CP r
@@ -384,47 +488,40 @@ impl Cpu {
             0xB8..=0xBD | 0xBF => {
                 let r = self.regs.get8(opcode.yyy());
                 alu::sub8(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // CP [HL]
             0xBE => {
-                let value = bus.read8(self.regs.hl());
+                let value = self.read8(bus, self.regs.hl());
                 alu::sub8(&mut self.regs.flags, self.regs.a, value);
-                8
             }
 
             // CP n
             0xFE => {
                 let value = self.fetch8(bus);
                 alu::sub8(&mut self.regs.flags, self.regs.a, value);
-                8
             }
 
             // INC r
             // 0b00xxx100
             0x04 | 0x14 | 0x24 | 0x0C | 0x1C | 0x2C | 0x3C => {
                 self.update_reg(opcode.xxx(), alu::inc8);
-                4
             }
 
             // INC [HL]
             0x34 => {
                 self.update_ram_hl(bus, alu::inc8);
-                12
             }
 
             // DEC r
             // 0b00xxx101
             0x05 | 0x15 | 0x25 | 0x0D | 0x1D | 0x2D | 0x3D => {
                 self.update_reg(opcode.xxx(), alu::dec8);
-                4
             }
 
             // DEC [HL]
             0x35 => {
                 self.update_ram_hl(bus, alu::dec8);
-                12
             }
 
             // AND r
@@ -432,21 +529,18 @@ impl Cpu {
             0xA0..=0xA5 | 0xA7 => {
                 let r = self.regs.get8(opcode.yyy());
                 self.regs.a = alu::bitwise_and(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // AND [HL]
             0xA6 => {
-                let x = bus.read8(self.regs.hl());
+                let x = self.read8(bus, self.regs.hl());
                 self.regs.a = alu::bitwise_and(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // AND n
             0xE6 => {
                 let x = self.fetch8(bus);
                 self.regs.a = alu::bitwise_and(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // OR r
@@ -454,21 +548,18 @@ impl Cpu {
             0xB0..=0xB5 | 0xB7 => {
                 let r = self.regs.get8(opcode.yyy());
                 self.regs.a = alu::bitwise_or(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // OR [HL]
             0xB6 => {
-                let x = bus.read8(self.regs.hl());
+                let x = self.read8(bus, self.regs.hl());
                 self.regs.a = alu::bitwise_or(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // OR n
             0xF6 => {
                 let x = self.fetch8(bus);
                 self.regs.a = alu::bitwise_or(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // XOR r
@@ -476,33 +567,28 @@ impl Cpu {
             0xA8..=0xAD | 0xAF => {
                 let r = self.regs.get8(opcode.yyy());
                 self.regs.a = alu::bitwise_xor(&mut self.regs.flags, self.regs.a, r);
-                4
             }
 
             // XOR [HL]
             0xAE => {
-                let x = bus.read8(self.regs.hl());
+                let x = self.read8(bus, self.regs.hl());
                 self.regs.a = alu::bitwise_xor(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // XOR n
             0xEE => {
                 let x = self.fetch8(bus);
                 self.regs.a = alu::bitwise_xor(&mut self.regs.flags, self.regs.a, x);
-                8
             }
 
             // DAA
             0x27 => {
                 self.regs.a = alu::daa(&mut self.regs.flags, self.regs.a);
-                4
             }
 
             // CPL
             0x2F => {
                 self.regs.a = alu::bitwise_not(&mut self.regs.flags, self.regs.a);
-                4
             }
 
             // --- 16-BIT ARITHMETIC/LOGIC INSTRUCTIONS ---
@@ -514,21 +600,21 @@ impl Cpu {
                 let rr = self.regs.get16_with_sp(opcode.rr());
                 let result = alu::add16(&mut self.regs.flags, hl, rr);
                 self.regs.set_hl(result);
-                8
+                self.tick(bus);
             }
 
             // INC rr
             0x03 | 0x13 | 0x23 | 0x33 => {
                 let rr = self.regs.get16_with_sp(opcode.rr());
                 self.regs.set16_with_sp(opcode.rr(), alu::inc16(rr));
-                8
+                self.tick_with_address(bus, rr);
             }
 
             // DEC rr
             0x0B | 0x1B | 0x2B | 0x3B => {
                 let rr = self.regs.get16_with_sp(opcode.rr());
                 self.regs.set16_with_sp(opcode.rr(), alu::dec16(rr));
-                8
+                self.tick_with_address(bus, rr);
             }
 
             // ADD SP, n
@@ -536,7 +622,8 @@ impl Cpu {
                 let n = self.fetch8(bus);
                 self.regs.sp =
                     alu::add16_with_signed_byte_operand(&mut self.regs.flags, self.regs.sp, n);
-                16
+                self.tick(bus);
+                self.tick(bus);
             }
 
             // --- ROTATE AND SHIFT INSTRUCTIONS ---
@@ -545,7 +632,6 @@ impl Cpu {
             0x07 => {
                 self.regs.a = alu::rotate_left(&mut self.regs.flags, self.regs.a);
                 self.regs.flags.set_zero(false); // for rotation instructions on register A, always zero flag = 0
-                4
             }
 
             // RLA
@@ -553,14 +639,12 @@ impl Cpu {
                 self.regs.a =
                     alu::rotate_left_through_carry_flag(&mut self.regs.flags, self.regs.a);
                 self.regs.flags.set_zero(false);
-                4
             }
 
             // RRCA
             0x0F => {
                 self.regs.a = alu::rotate_right(&mut self.regs.flags, self.regs.a);
                 self.regs.flags.set_zero(false);
-                4
             }
 
             // RRA
@@ -568,7 +652,6 @@ impl Cpu {
                 self.regs.a =
                     alu::rotate_right_through_carry_flag(&mut self.regs.flags, self.regs.a);
                 self.regs.flags.set_zero(false);
-                4
             }
 
             // --- JUMP INSTRUCTIONS ---
@@ -576,13 +659,12 @@ impl Cpu {
             // JP nn
             0xC3 => {
                 self.regs.pc = self.fetch16(bus);
-                16
+                self.tick(bus);
             }
 
             // JP HL
             0xE9 => {
                 self.regs.pc = self.regs.hl();
-                4
             }
 
             // JP flag, nn
@@ -591,9 +673,7 @@ impl Cpu {
 
                 if self.evaluate_flag_condition(opcode.ff()) {
                     self.regs.pc = nn;
-                    16
-                } else {
-                    12
+                    self.tick(bus);
                 }
             }
 
@@ -601,7 +681,7 @@ impl Cpu {
             0x18 => {
                 let n = self.fetch8(bus) as i8 as i32;
                 self.regs.pc = ((self.regs.pc as u32 as i32) + n) as u16;
-                12
+                self.tick(bus);
             }
 
             // JR flag, n
@@ -610,18 +690,14 @@ impl Cpu {
 
                 if self.evaluate_flag_condition(opcode.ff()) {
                     self.regs.pc = ((self.regs.pc as u32 as i32) + n) as u16;
-                    12
-                } else {
-                    8
+                    self.tick(bus);
                 }
             }
 
             // CALL nn
             0xCD => {
                 let nn = self.fetch16(bus);
-                self.stack_push(bus, self.regs.pc);
-                self.regs.pc = nn;
-                24
+                self.call(bus, nn, FrameKind::Call);
             }
 
             // CALL flag, nn
@@ -629,43 +705,37 @@ impl Cpu {
                 let nn = self.fetch16(bus);
 
                 if self.evaluate_flag_condition(opcode.ff()) {
-                    self.stack_push(bus, self.regs.pc);
-                    self.regs.pc = nn;
-                    24
-                } else {
-                    12
+                    self.call(bus, nn, FrameKind::Call);
                 }
             }
 
             // RET
             0xC9 => {
-                self.regs.pc = self.stack_pop(bus);
-                16
+                self.ret(bus);
+                self.tick(bus);
             }
 
             // RET flag
             0xC0 | 0xC8 | 0xD0 | 0xD8 => {
+                self.tick(bus); // evaluate condition
+
                 if self.evaluate_flag_condition(opcode.ff()) {
-                    self.regs.pc = self.stack_pop(bus);
-                    20
-                } else {
-                    8
+                    self.ret(bus);
+                    self.tick(bus);
                 }
             }
 
             // RETI
             0xD9 => {
-                self.regs.pc = self.stack_pop(bus);
+                self.ret(bus);
+                self.tick(bus);
                 self.ime.enable(0);
-                16
             }
 
             // RST n
             0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => {
                 let n = opcode.0 - 0xC7;
-                self.stack_push(bus, self.regs.pc);
-                self.regs.pc = n as u16;
-                16
+                self.call(bus, n as u16, FrameKind::Rst);
             }
 
             // --- CPU CONTROL INSTRUCTIONS ---
@@ -675,7 +745,6 @@ impl Cpu {
                 self.regs.flags.set_subtraction(false);
                 self.regs.flags.set_half_carry(false);
                 self.regs.flags.toggle_carry();
-                4
             }
 
             // SCF
@@ -683,59 +752,57 @@ impl Cpu {
                 self.regs.flags.set_subtraction(false);
                 self.regs.flags.set_half_carry(false);
                 self.regs.flags.set_carry(true);
-                4
             }
 
             // NOP
-            0x00 => 4,
+            0x00 => {}
 
             // HALT
             0x76 => {
-                self.halted = true;
-                4
+                if !self.ime.enabled() && bus.interrupts.next_triggered_interrupt().is_some() {
+                    // HALT mode is not entered and the HALT bug occurs (note that this includes when HALT immediately
+                    // follows EI, in which case the handler will return to the HALT instruction)
+                    log::debug!("HALT bug triggered");
+                    self.halt_bug = true;
+                } else {
+                    self.state = State::Halted;
+                }
             }
 
             // STOP
-            0x10 => {
-                let n = self.fetch8(bus);
-                if n != 0x00 {
-                    log::warn!(
-                        "STOP instruction not followed by null byte - instead encountered {:#04X}",
-                        n
-                    );
-                    self.regs.pc -= 1; // go back so that the fetched byte does get executed
-                }
-                self.halted = true; // TODO
-                4
-            }
+            0x10 => self.stop(bus),
 
             // DI
             0xF3 => {
                 self.ime.disable(1);
-                4
             }
 
             // EI
             0xFB => {
                 self.ime.enable(1);
-                4
             }
 
             // CB prefix instructions
             0xCB => {
                 let suffix = Opcode(self.fetch8(bus));
                 log::trace!("following the 0xCB prefix is {}", suffix);
-                self.execute_cb(suffix, bus)
+                self.execute_cb(suffix, bus);
             }
 
             0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
-                log::warn!("unknown opcode {} encountered", opcode);
-                4
+                let address = self.regs.pc.wrapping_sub(1);
+                log::error!(
+                    "illegal opcode {opcode} encountered at address {address:#06X} so locking up"
+                );
+                self.state = State::Locked {
+                    address,
+                    opcode: opcode.0,
+                };
             }
         }
     }
 
-    fn execute_cb(&mut self, opcode: Opcode, bus: &mut MemoryBus) -> Cycles {
+    fn execute_cb(&mut self, opcode: Opcode, bus: &mut MemoryBus) {
         match opcode.0 {
             // --- ROTATE AND SHIFT INSTRUCTIONS ---
 
@@ -743,104 +810,88 @@ impl Cpu {
             // 0b00000yyy
             0x00..=0x05 | 0x07 => {
                 self.update_reg(opcode.yyy(), alu::rotate_left);
-                8
             }
 
             // RLC [HL]
             0x06 => {
                 self.update_ram_hl(bus, alu::rotate_left);
-                16
             }
 
             // RL r
             // 0b00010yyy
             0x10..=0x15 | 0x17 => {
                 self.update_reg(opcode.yyy(), alu::rotate_left_through_carry_flag);
-                8
             }
 
             // RL [HL]
             0x16 => {
                 self.update_ram_hl(bus, alu::rotate_left_through_carry_flag);
-                16
             }
 
             // RRC r
             // 0b00001yyy
             0x08..=0x0D | 0x0F => {
                 self.update_reg(opcode.yyy(), alu::rotate_right);
-                8
             }
 
             // RRC [HL]
             0x0E => {
                 self.update_ram_hl(bus, alu::rotate_right);
-                16
             }
 
             // RR r
             // 0b00011yyy
             0x18..=0x1D | 0x1F => {
                 self.update_reg(opcode.yyy(), alu::rotate_right_through_carry_flag);
-                8
             }
 
             // RR [HL]
             0x1E => {
                 self.update_ram_hl(bus, alu::rotate_right_through_carry_flag);
-                16
             }
 
             // SLA r
             // 0b00100yyy
             0x20..=0x25 | 0x27 => {
                 self.update_reg(opcode.yyy(), alu::shift_left);
-                8
             }
 
             // SLA [HL]
             0x26 => {
                 self.update_ram_hl(bus, alu::shift_left);
-                16
             }
 
             // SWAP r
             // 0b00110yyy
             0x30..=0x35 | 0x37 => {
                 self.update_reg(opcode.yyy(), alu::swap_nibbles);
-                8
             }
 
             // SWAP [HL]
             0x36 => {
                 self.update_ram_hl(bus, alu::swap_nibbles);
-                16
             }
 
             // SRA r
             // 0b00101yyy
             0x28..=0x2D | 0x2F => {
                 self.update_reg(opcode.yyy(), alu::shift_right_leave_msb);
-                8
             }
 
             // SRA [HL]
             0x2E => {
                 self.update_ram_hl(bus, alu::shift_right_leave_msb);
-                16
             }
 
             // SRL r
             // 0b00111yyy
             0x38..=0x3D | 0x3F => {
                 self.update_reg(opcode.yyy(), alu::shift_right_clear_msb);
-                8
             }
 
             // SRL [HL]
             0x3E => {
                 self.update_ram_hl(bus, alu::shift_right_clear_msb);
-                16
             }
 
             // --- SINGLE-BIT OPERATION INSTRUCTIONS ---
@@ -858,15 +909,13 @@ impl Cpu {
             | 0x7F => {
                 let r = self.regs.get8(opcode.yyy());
                 alu::test_bit(&mut self.regs.flags, r, opcode.xxx());
-                8
             }
 
             // BIT n, [HL]
             // 0b01xxx110
             0x46 | 0x4E | 0x56 | 0x5E | 0x66 | 0x6E | 0x76 | 0x7E => {
-                let value = bus.read8(self.regs.hl());
+                let value = self.read8(bus, self.regs.hl());
                 alu::test_bit(&mut self.regs.flags, value, opcode.xxx());
-                12
             }
 
             // SET n, r
@@ -883,16 +932,14 @@ impl Cpu {
                 let r = self.regs.get8(opcode.yyy());
                 let result = modify_bit(r, opcode.xxx(), true);
                 self.regs.set8(opcode.yyy(), result);
-                8
             }
 
             // SET n, [HL]
             // 0b11xxx110
             0xC6 | 0xCE | 0xD6 | 0xDE | 0xE6 | 0xEE | 0xF6 | 0xFE => {
-                let value = bus.read8(self.regs.hl());
+                let value = self.read8(bus, self.regs.hl());
                 let result = modify_bit(value, opcode.xxx(), true);
-                bus.write8(self.regs.hl(), result);
-                16
+                self.write8(bus, self.regs.hl(), result);
             }
 
             // RES n, r
@@ -909,41 +956,161 @@ impl Cpu {
                 let r = self.regs.get8(opcode.yyy());
                 let result = modify_bit(r, opcode.xxx(), false);
                 self.regs.set8(opcode.yyy(), result);
-                8
             }
 
             // RES n, [HL]
             // 0b10xxx110
             0x86 | 0x8E | 0x96 | 0x9E | 0xA6 | 0xAE | 0xB6 | 0xBE => {
-                let value = bus.read8(self.regs.hl());
+                let value = self.read8(bus, self.regs.hl());
                 let result = modify_bit(value, opcode.xxx(), false);
-                bus.write8(self.regs.hl(), result);
-                16
+                self.write8(bus, self.regs.hl(), result);
             }
         }
     }
 
-    fn fetch8(&mut self, bus: &MemoryBus) -> u8 {
-        let value = bus.read8(self.regs.pc);
-        self.regs.pc += 1;
+    /// Execute the STOP instruction. Depending on whether a button is held, whether an interrupt is pending, and whether
+    /// a CGB speed switch has been requested, STOP may be either a 1 or 2 byte instruction and may enter STOP mode, HALT
+    /// mode, or neither.
+    fn stop(&mut self, bus: &mut MemoryBus) {
+        let button_held = bus.joypad.input_lines() != 0xF;
+        let interrupt_pending = bus.interrupts.next_triggered_interrupt().is_some();
+
+        // when no interrupt is pending, the byte following STOP is skipped
+        if !interrupt_pending {
+            self.regs.pc += 1;
+        }
+
+        if button_held {
+            if !interrupt_pending {
+                log::debug!("STOP executed with button held so entering HALT mode");
+                self.state = State::Halted;
+            }
+            return;
+        }
+
+        bus.reset_divider();
+
+        if bus.speed_switch_armed() {
+            if interrupt_pending && self.ime.enabled() {
+                // on hardware the CPU glitches non-deterministically so just perform the switch as if IME=0
+                log::warn!("speed switch performed with IME=1 and an interrupt pending");
+            }
+
+            bus.switch_speed();
+
+            if !interrupt_pending {
+                for _ in 0..SPEED_SWITCH_PAUSE {
+                    self.tick(bus);
+                }
+            }
+        } else {
+            log::debug!("entering STOP mode");
+            self.state = State::Stopped;
+            bus.stop();
+        }
+    }
+
+    /// Perform a single M-cycle in which the CPU does not access memory, updating the rest of the system accordingly.
+    fn tick(&mut self, bus: &mut MemoryBus) {
+        bus.update(M_CYCLE);
+        self.cycles += M_CYCLE;
+    }
+
+    /// Perform a single M-cycle in which the CPU does not access memory but a 16-bit increment/decrement places the given
+    /// address on the address bus (which can trigger the OAM corruption bug).
+    fn tick_with_address(&mut self, bus: &mut MemoryBus, addr: u16) {
+        self.tick(bus);
+        bus.corrupt_oam(addr, OamCorruption::Write);
+    }
+
+    /// Read a byte from memory, taking a single M-cycle.
+    fn read8(&mut self, bus: &mut MemoryBus, addr: u16) -> u8 {
+        let value = self.read8_instruction(bus, addr);
+        bus.debugger.watch(addr, Access::Read, value);
         value
     }
 
-    fn fetch16(&mut self, bus: &MemoryBus) -> u16 {
-        let value = bus.read16(self.regs.pc);
-        self.regs.pc += 2;
+    /// Read a byte of an instruction (opcode or operand), taking a single M-cycle. Unlike [`Cpu::read8`], this does not
+    /// trigger read watchpoints.
+    fn read8_instruction(&mut self, bus: &mut MemoryBus, addr: u16) -> u8 {
+        self.tick(bus);
+        bus.corrupt_oam(addr, OamCorruption::Read);
+        bus.read8(addr)
+    }
+
+    /// Read a byte from memory while the register holding the address is incremented/decremented in the same M-cycle.
+    fn read8_increase(&mut self, bus: &mut MemoryBus, addr: u16) -> u8 {
+        self.tick(bus);
+        bus.corrupt_oam(addr, OamCorruption::ReadIncrease);
+        let value = bus.read8(addr);
+        bus.debugger.watch(addr, Access::Read, value);
+        value
+    }
+
+    /// Write a byte to memory, taking a single M-cycle.
+    fn write8(&mut self, bus: &mut MemoryBus, addr: u16, value: u8) {
+        self.tick(bus);
+        bus.corrupt_oam(addr, OamCorruption::Write);
+        bus.write8(addr, value);
+        bus.debugger.watch(addr, Access::Write, value);
+    }
+
+    fn fetch8(&mut self, bus: &mut MemoryBus) -> u8 {
+        let value = self.read8_instruction(bus, self.regs.pc);
+        self.regs.pc += 1;
         value
     }
 
+    fn fetch16(&mut self, bus: &mut MemoryBus) -> u16 {
+        let lsb = self.fetch8(bus);
+        let msb = self.fetch8(bus);
+        u16::from_be_bytes([msb, lsb])
+    }
+
+    /// Push a value to the stack. This takes 3 M-cycles - SP is decremented during an internal delay before the two
+    /// bytes are written (most significant byte first).
     fn stack_push(&mut self, bus: &mut MemoryBus, value: u16) {
-        self.regs.sp -= 2;
-        bus.write16(self.regs.sp, value);
+        let [msb, lsb] = value.to_be_bytes();
+        self.tick_with_address(bus, self.regs.sp);
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write8(bus, self.regs.sp, msb);
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.write8(bus, self.regs.sp, lsb);
     }
 
     fn stack_pop(&mut self, bus: &mut MemoryBus) -> u16 {
-        let value = bus.read16(self.regs.sp);
-        self.regs.sp += 2;
-        value
+        let lsb = self.read8_increase(bus, self.regs.sp);
+        self.regs.sp = self.regs.sp.wrapping_add(1);
+        let msb = self.read8(bus, self.regs.sp);
+        self.regs.sp = self.regs.sp.wrapping_add(1);
+        u16::from_be_bytes([msb, lsb])
+    }
+
+    /// Push PC to the stack and jump to the given address, recording the call on the call stack.
+    fn call(&mut self, bus: &mut MemoryBus, target: u16, kind: FrameKind) {
+        let return_address = self.regs.pc;
+        self.stack_push(bus, return_address);
+        self.regs.pc = target;
+
+        self.call_stack.push(Frame {
+            kind,
+            target,
+            target_bank: bus.rom_bank(target),
+            return_address,
+            return_bank: bus.rom_bank(return_address),
+            sp: self.regs.sp,
+        });
+    }
+
+    /// Pop the return address from the stack into PC, checking that it matches the call stack.
+    fn ret(&mut self, bus: &mut MemoryBus) {
+        let sp = self.regs.sp;
+        self.regs.pc = self.stack_pop(bus);
+
+        if let Err(imbalance) = self.call_stack.ret(self.regs.pc, sp) {
+            log::debug!("{imbalance}");
+            bus.debugger.stack_imbalance(imbalance);
+        }
     }
 
     fn evaluate_flag_condition(&self, ff: u8) -> bool {
@@ -963,9 +1130,9 @@ impl Cpu {
     }
 
     fn update_ram_hl(&mut self, bus: &mut MemoryBus, f: impl Fn(&mut Flags, u8) -> u8) {
-        let x = bus.read8(self.regs.hl());
+        let x = self.read8(bus, self.regs.hl());
         let result = f(&mut self.regs.flags, x);
-        bus.write8(self.regs.hl(), result);
+        self.write8(bus, self.regs.hl(), result);
     }
 }
 
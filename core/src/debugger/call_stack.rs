@@ -0,0 +1,214 @@
+use std::fmt;
+
+use crate::interrupts::Interrupt;
+
+/// Maximum number of frames kept. Should a game call deeper than this (or never return from calls, as can happen when
+/// it manipulates the stack directly), the outermost frames are forgotten.
+const MAX_FRAMES: usize = 256;
+
+/// How a frame of the call stack was entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// A CALL instruction.
+    Call,
+    /// An RST instruction.
+    Rst,
+    /// Dispatch of an interrupt handler.
+    Interrupt(Interrupt),
+}
+
+impl fmt::Display for FrameKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameKind::Call => write!(f, "call"),
+            FrameKind::Rst => write!(f, "rst"),
+            FrameKind::Interrupt(int) => write!(f, "{int}"),
+        }
+    }
+}
+
+/// A call made (or interrupt handler dispatched) that has yet to return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    /// Address of the called subroutine or interrupt handler.
+    pub target: u16,
+    /// ROM bank mapped to the target address at the time of the call.
+    pub target_bank: u16,
+    /// Address pushed to the stack, to which the subroutine is expected to return.
+    pub return_address: u16,
+    /// ROM bank mapped to the return address at the time of the call.
+    pub return_bank: u16,
+    /// Value of SP after the return address was pushed (i.e., the address at which it is stored).
+    pub sp: u16,
+}
+
+/// A return that does not match the innermost frame of the call stack - either the return address was read from a
+/// different position on the stack (e.g., SP was adjusted or the stack switched) or the return address itself was
+/// overwritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackImbalance {
+    /// Address returned to.
+    pub address: u16,
+    /// Value of SP from which the return address was popped.
+    pub sp: u16,
+    /// Return address of the innermost frame at the time of the return (`None` if the call stack was empty).
+    pub expected: Option<u16>,
+}
+
+impl fmt::Display for StackImbalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "stack imbalance: returned to {:#06X} from SP={:#06X}",
+            self.address, self.sp
+        )?;
+        match self.expected {
+            Some(expected) => write!(f, " (expected {expected:#06X})"),
+            None => write!(f, " (no call to return from)"),
+        }
+    }
+}
+
+/// Shadow call stack maintained by the CPU as calls are made and interrupts dispatched, used to produce backtraces
+/// and detect returns that don't match the calls made.
+#[derive(Debug, Clone, Default)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+}
+
+impl CallStack {
+    pub fn new() -> Self {
+        CallStack::default()
+    }
+
+    /// The frames of the call stack, outermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// The frames of the call stack, innermost (most recent) first.
+    pub fn backtrace(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter().rev()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub(crate) fn push(&mut self, frame: Frame) {
+        // frames at or below the new return address can no longer be returned from (e.g., SP was reloaded)
+        while self.frames.last().is_some_and(|last| last.sp <= frame.sp) {
+            self.frames.pop();
+        }
+
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.remove(0);
+        }
+        self.frames.push(frame);
+    }
+
+    /// Handle a return to the given address, popped from the stack at the given value of SP. Frames whose return
+    /// addresses were stored below SP are discarded as they can no longer be returned from. Returns the frame returned
+    /// from, or the imbalance should the return not match the innermost frame.
+    pub(crate) fn ret(&mut self, address: u16, sp: u16) -> Result<Frame, StackImbalance> {
+        let imbalance = StackImbalance {
+            address,
+            sp,
+            expected: self.frames.last().map(|frame| frame.return_address),
+        };
+
+        let mut discarded = false;
+        while self.frames.last().is_some_and(|last| last.sp < sp) {
+            self.frames.pop();
+            discarded = true;
+        }
+
+        match self.frames.last() {
+            Some(frame) if frame.sp == sp => {
+                let frame = self.frames.pop().unwrap();
+                if discarded || frame.return_address != address {
+                    Err(imbalance)
+                } else {
+                    Ok(frame)
+                }
+            }
+            _ => Err(imbalance),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(return_address: u16, sp: u16) -> Frame {
+        Frame {
+            kind: FrameKind::Call,
+            target: 0x4000,
+            target_bank: 1,
+            return_address,
+            return_bank: 0,
+            sp,
+        }
+    }
+
+    #[test]
+    fn balanced() {
+        let mut stack = CallStack::new();
+        stack.push(call(0x0153, 0xFFFC));
+        stack.push(call(0x4003, 0xFFFA));
+        assert_eq!(stack.depth(), 2);
+        assert_eq!(stack.backtrace().next().unwrap().return_address, 0x4003);
+
+        assert_eq!(stack.ret(0x4003, 0xFFFA), Ok(call(0x4003, 0xFFFA)));
+        assert_eq!(stack.ret(0x0153, 0xFFFC), Ok(call(0x0153, 0xFFFC)));
+        assert_eq!(stack.depth(), 0);
+    }
+
+    #[test]
+    fn imbalances() {
+        let mut stack = CallStack::new();
+
+        // returning without a call (e.g., using PUSH and RET to jump)
+        assert_eq!(
+            stack.ret(0x1234, 0xFFFC),
+            Err(StackImbalance {
+                address: 0x1234,
+                sp: 0xFFFC,
+                expected: None
+            })
+        );
+
+        // overwritten return address
+        stack.push(call(0x0153, 0xFFFC));
+        assert_eq!(
+            stack.ret(0x2000, 0xFFFC),
+            Err(StackImbalance {
+                address: 0x2000,
+                sp: 0xFFFC,
+                expected: Some(0x0153)
+            })
+        );
+        assert_eq!(stack.depth(), 0);
+
+        // returning to the caller's caller by popping the inner return address
+        stack.push(call(0x0153, 0xFFFC));
+        stack.push(call(0x4003, 0xFFFA));
+        assert!(stack.ret(0x0153, 0xFFFC).is_err());
+        assert_eq!(stack.depth(), 0);
+
+        // returning an address pushed by the subroutine itself leaves the frame in place
+        stack.push(call(0x0153, 0xFFFC));
+        assert!(stack.ret(0x0200, 0xFFFA).is_err());
+        assert_eq!(stack.depth(), 1);
+
+        // frames abandoned by reloading SP are discarded by the next call
+        stack.push(call(0x0300, 0xFFFC));
+        assert_eq!(stack.depth(), 1);
+    }
+}
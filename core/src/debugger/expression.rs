@@ -0,0 +1,591 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bus::MemoryBus;
+use crate::cpu::Cpu;
+use crate::io;
+use crate::symbols::SymbolTable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    A,
+    F,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    Af,
+    Bc,
+    De,
+    Hl,
+    Sp,
+    Pc,
+    ZeroFlag,
+    SubtractionFlag,
+    HalfCarryFlag,
+    CarryFlag,
+}
+
+const REGISTERS: [(&str, Register); 18] = [
+    ("A", Register::A),
+    ("F", Register::F),
+    ("B", Register::B),
+    ("C", Register::C),
+    ("D", Register::D),
+    ("E", Register::E),
+    ("H", Register::H),
+    ("L", Register::L),
+    ("AF", Register::Af),
+    ("BC", Register::Bc),
+    ("DE", Register::De),
+    ("HL", Register::Hl),
+    ("SP", Register::Sp),
+    ("PC", Register::Pc),
+    ("ZF", Register::ZeroFlag),
+    ("NF", Register::SubtractionFlag),
+    ("HF", Register::HalfCarryFlag),
+    ("CF", Register::CarryFlag),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Negate,
+    Not,
+    Complement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    BitOr,
+    BitXor,
+    BitAnd,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+/// Binary operators in order of increasing precedence (as in C).
+const BINARY_OPS: [&[(&str, BinaryOp)]; 10] = [
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("==", BinaryOp::Equal), ("!=", BinaryOp::NotEqual)],
+    &[
+        ("<=", BinaryOp::LessEqual),
+        (">=", BinaryOp::GreaterEqual),
+        ("<", BinaryOp::Less),
+        (">", BinaryOp::Greater),
+    ],
+    &[("<<", BinaryOp::ShiftLeft), (">>", BinaryOp::ShiftRight)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)],
+    &[
+        ("*", BinaryOp::Multiply),
+        ("/", BinaryOp::Divide),
+        ("%", BinaryOp::Remainder),
+    ],
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    /// The byte in memory at the address given by the inner expression.
+    Memory(Box<Node>),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn eval(&self, cpu: &Cpu, bus: &MemoryBus) -> i64 {
+        match self {
+            Node::Number(value) => *value,
+            Node::Register(reg) => register_value(*reg, cpu),
+            Node::Memory(addr) => bus.read8(addr.eval(cpu, bus) as u16) as i64,
+            Node::Unary(op, operand) => {
+                let value = operand.eval(cpu, bus);
+                match op {
+                    UnaryOp::Negate => value.wrapping_neg(),
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::Complement => !value,
+                }
+            }
+            Node::Binary(BinaryOp::Or, lhs, rhs) => {
+                (lhs.eval(cpu, bus) != 0 || rhs.eval(cpu, bus) != 0) as i64
+            }
+            Node::Binary(BinaryOp::And, lhs, rhs) => {
+                (lhs.eval(cpu, bus) != 0 && rhs.eval(cpu, bus) != 0) as i64
+            }
+            Node::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(cpu, bus), rhs.eval(cpu, bus));
+                match op {
+                    BinaryOp::Equal => (lhs == rhs) as i64,
+                    BinaryOp::NotEqual => (lhs != rhs) as i64,
+                    BinaryOp::Less => (lhs < rhs) as i64,
+                    BinaryOp::LessEqual => (lhs <= rhs) as i64,
+                    BinaryOp::Greater => (lhs > rhs) as i64,
+                    BinaryOp::GreaterEqual => (lhs >= rhs) as i64,
+                    BinaryOp::BitOr => lhs | rhs,
+                    BinaryOp::BitXor => lhs ^ rhs,
+                    BinaryOp::BitAnd => lhs & rhs,
+                    BinaryOp::ShiftLeft => lhs.wrapping_shl(rhs as u32),
+                    BinaryOp::ShiftRight => lhs.wrapping_shr(rhs as u32),
+                    BinaryOp::Add => lhs.wrapping_add(rhs),
+                    BinaryOp::Subtract => lhs.wrapping_sub(rhs),
+                    BinaryOp::Multiply => lhs.wrapping_mul(rhs),
+                    BinaryOp::Divide => lhs.checked_div(rhs).unwrap_or(0),
+                    BinaryOp::Remainder => lhs.checked_rem(rhs).unwrap_or(0),
+                    BinaryOp::Or | BinaryOp::And => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+fn register_value(reg: Register, cpu: &Cpu) -> i64 {
+    let regs = &cpu.regs;
+    let value = match reg {
+        Register::A => regs.a as u16,
+        Register::F => regs.flags.0 as u16,
+        Register::B => regs.b as u16,
+        Register::C => regs.c as u16,
+        Register::D => regs.d as u16,
+        Register::E => regs.e as u16,
+        Register::H => regs.h as u16,
+        Register::L => regs.l as u16,
+        Register::Af => regs.af(),
+        Register::Bc => regs.bc(),
+        Register::De => regs.de(),
+        Register::Hl => regs.hl(),
+        Register::Sp => regs.sp,
+        Register::Pc => regs.pc,
+        Register::ZeroFlag => regs.flags.zero() as u16,
+        Register::SubtractionFlag => regs.flags.subtraction() as u16,
+        Register::HalfCarryFlag => regs.flags.half_carry() as u16,
+        Register::CarryFlag => regs.flags.carry() as u16,
+    };
+    value as i64
+}
+
+/// An expression over the state of the system, used as the condition of a breakpoint or watchpoint.
+///
+/// Expressions are made up of:
+/// * Numbers - decimal (`60`), hexadecimal (`0x3C` or `$3C`), or binary (`%00111100`).
+/// * CPU registers (`A`, `HL`, `SP`, etc.) and flags (`ZF`, `NF`, `HF`, and `CF`, each either 0 or 1).
+/// * IO register names (e.g., `LY` or `LCDC`), which give the value of that register.
+/// * Labels from the symbol table, which give the address of that label.
+/// * Memory dereferences - `[HL]` gives the byte at the address in HL.
+/// * The operators of C (`||`, `&&`, `|`, `^`, `&`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `<<`, `>>`, `+`, `-`, `*`, `/`,
+///   `%`, and the unary `-`, `!`, and `~`) with the same precedence, plus parentheses.
+///
+/// Names of registers are case insensitive whereas labels are case sensitive. Comparisons and logical operators give 1
+/// when true and 0 when false, and division by zero gives 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    text: String,
+    root: Node,
+}
+
+impl Expression {
+    /// Parse an expression, resolving any labels using the given symbol table.
+    pub fn parse(text: &str, symbols: &SymbolTable) -> Result<Self, ParseExpressionError> {
+        let mut parser = Parser {
+            text,
+            position: 0,
+            symbols,
+        };
+        let root = parser.expression(0)?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+
+        Ok(Expression {
+            text: text.trim().to_string(),
+            root,
+        })
+    }
+
+    pub fn eval(&self, cpu: &Cpu, bus: &MemoryBus) -> i64 {
+        self.root.eval(cpu, bus)
+    }
+
+    /// Whether the expression evaluates to a non-zero value.
+    pub fn is_true(&self, cpu: &Cpu, bus: &MemoryBus) -> bool {
+        self.eval(cpu, bus) != 0
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ParseExpressionError;
+
+    /// Parse an expression without any labels.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Expression::parse(s, &SymbolTable::new())
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+    symbols: &'a SymbolTable,
+}
+
+impl<'a> Parser<'a> {
+    /// Parse a sequence of operands separated by binary operators with at least the given precedence.
+    fn expression(&mut self, precedence: usize) -> Result<Node, ParseExpressionError> {
+        if precedence == BINARY_OPS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.expression(precedence + 1)?;
+        while let Some(op) = self.binary_op(precedence) {
+            let rhs = self.expression(precedence + 1)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn binary_op(&mut self, precedence: usize) -> Option<BinaryOp> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let (symbol, op) = BINARY_OPS[precedence]
+            .iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))?;
+
+        // don't mistake the start of a longer operator for a shorter one (e.g., `&&` for `&` or `<<` for `<`)
+        let longer = BINARY_OPS
+            .iter()
+            .flat_map(|ops| ops.iter())
+            .any(|(other, _)| other.len() > symbol.len() && rest.starts_with(other));
+        if longer {
+            return None;
+        }
+
+        self.position += symbol.len();
+        Some(*op)
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseExpressionError> {
+        self.skip_whitespace();
+        let op = match self.peek() {
+            Some('-') => UnaryOp::Negate,
+            Some('!') => UnaryOp::Not,
+            Some('~') => UnaryOp::Complement,
+            _ => return self.operand(),
+        };
+        self.position += 1;
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn operand(&mut self) -> Result<Node, ParseExpressionError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let inner = self.expression(0)?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some('[') => {
+                self.position += 1;
+                let inner = self.expression(0)?;
+                self.expect(']')?;
+                Ok(Node::Memory(Box::new(inner)))
+            }
+            Some('$') => {
+                self.position += 1;
+                self.number(16)
+            }
+            Some('%') => {
+                self.position += 1;
+                self.number(2)
+            }
+            Some('0') if self.rest()[1..].starts_with(['x', 'X']) => {
+                self.position += 2;
+                self.number(16)
+            }
+            Some(c) if c.is_ascii_digit() => self.number(10),
+            Some(c) if is_identifier_char(c) => self.identifier(),
+            Some(_) => Err(self.error("expected a number, register, or label")),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    fn number(&mut self, radix: u32) -> Result<Node, ParseExpressionError> {
+        let digits = self.take_while(|c| c.is_ascii_alphanumeric());
+        i64::from_str_radix(digits, radix)
+            .map(Node::Number)
+            .map_err(|_| ParseExpressionError {
+                position: self.position - digits.len(),
+                reason: format!("invalid number '{digits}'"),
+            })
+    }
+
+    fn identifier(&mut self) -> Result<Node, ParseExpressionError> {
+        let name = self.take_while(is_identifier_char);
+
+        if let Some((_, reg)) = REGISTERS
+            .iter()
+            .find(|(reg, _)| reg.eq_ignore_ascii_case(name))
+        {
+            Ok(Node::Register(*reg))
+        } else if let Some(addr) = io::register_address(name) {
+            Ok(Node::Memory(Box::new(Node::Number(addr as i64))))
+        } else if let Some(symbol) = self.symbols.get(name) {
+            Ok(Node::Number(symbol.address as i64))
+        } else {
+            Err(ParseExpressionError {
+                position: self.position - name.len(),
+                reason: format!("unknown register or label '{name}'"),
+            })
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseExpressionError> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = &self.text[self.position..];
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, reason: &str) -> ParseExpressionError {
+        ParseExpressionError {
+            position: self.position,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '@' | '#')
+}
+
+/// A message logged by a tracepoint. Expressions within braces are replaced by their values when the message is
+/// formatted (e.g., `A is {A} and [HL] is {[HL]}`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    text: String,
+    parts: Vec<MessagePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MessagePart {
+    Text(String),
+    Expression(Expression),
+}
+
+impl Message {
+    /// Parse a message, resolving any labels within its expressions using the given symbol table.
+    pub fn parse(text: &str, symbols: &SymbolTable) -> Result<Self, ParseExpressionError> {
+        let mut parts = Vec::new();
+        let mut rest = text;
+        let mut offset = 0;
+
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map(|len| start + len)
+                .ok_or_else(|| ParseExpressionError {
+                    position: offset + start,
+                    reason: "expected '}'".to_string(),
+                })?;
+
+            if start > 0 {
+                parts.push(MessagePart::Text(rest[..start].to_string()));
+            }
+            let expression = Expression::parse(&rest[start + 1..end], symbols).map_err(|e| {
+                ParseExpressionError {
+                    position: offset + start + 1 + e.position,
+                    ..e
+                }
+            })?;
+            parts.push(MessagePart::Expression(expression));
+
+            offset += end + 1;
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(MessagePart::Text(rest.to_string()));
+        }
+
+        Ok(Message {
+            text: text.to_string(),
+            parts,
+        })
+    }
+
+    /// Format the message with the values of its expressions given in hexadecimal.
+    pub fn format(&self, cpu: &Cpu, bus: &MemoryBus) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                MessagePart::Text(text) => text.clone(),
+                MessagePart::Expression(expression) => match expression.eval(cpu, bus) {
+                    value @ 0..=0xFF => format!("${value:02X}"),
+                    value @ 0x100..=0xFFFF => format!("${value:04X}"),
+                    value => value.to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseExpressionError {
+    /// Byte offset into the text at which the error occurred.
+    pub position: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at position {}: {}", self.position, self.reason)
+    }
+}
+
+impl std::error::Error for ParseExpressionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cartridge::Cartridge;
+    use crate::{mbc, Model};
+
+    fn system() -> (Cpu, MemoryBus) {
+        let mbc = mbc::from_cartridge(Cartridge::from_data(vec![0; 0x8000])).unwrap();
+        (
+            Cpu::new(),
+            MemoryBus::new(mbc, Model::Dmg, Default::default()),
+        )
+    }
+
+    fn eval(text: &str) -> i64 {
+        let (cpu, bus) = system();
+        text.parse::<Expression>().unwrap().eval(&cpu, &bus)
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("0x10 | $01 | %100"), 0x15);
+        assert_eq!(eval("1 << 4 == 16"), 1);
+        assert_eq!(eval("1 << 2 + 1"), 8);
+        assert_eq!(eval("0x80 >> 4 - 1 < 0x20"), 1);
+        assert_eq!(eval("3 & 1 && 0 || 1"), 1);
+        assert_eq!(eval("-1 < 0"), 1);
+        assert_eq!(eval("!5 + ~0"), -1);
+        assert_eq!(eval("10 / 0"), 0);
+        assert_eq!(eval("10 % 4 >= 2"), 1);
+    }
+
+    #[test]
+    fn system_state() {
+        let (mut cpu, mut bus) = system();
+        cpu.regs.a = 0x3C;
+        cpu.regs.set_hl(0xC000);
+        bus.write8(0xC000, 0x12);
+
+        let expr: Expression = "A == 0x3C && [HL] != 0".parse().unwrap();
+        assert!(expr.is_true(&cpu, &bus));
+        bus.write8(0xC000, 0);
+        assert!(!expr.is_true(&cpu, &bus));
+
+        let flags: Expression = "zf + cf * 2 + [hl]".parse().unwrap();
+        assert_eq!(flags.eval(&cpu, &bus), 3); // Z and C flags set at boot
+        assert_eq!(flags.to_string(), "zf + cf * 2 + [hl]");
+
+        let ly: Expression = "LY == 0x90".parse().unwrap();
+        assert!(!ly.is_true(&cpu, &bus));
+    }
+
+    #[test]
+    fn labels() {
+        let symbols: SymbolTable = "00:C000 wCounter\n".parse().unwrap();
+        let (cpu, mut bus) = system();
+        bus.write8(0xC000, 7);
+
+        let expr = Expression::parse("[wCounter] == 7", &symbols).unwrap();
+        assert!(expr.is_true(&cpu, &bus));
+
+        let err = "[wCounter]".parse::<Expression>().unwrap_err();
+        assert_eq!(err.position, 1);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!("1 +".parse::<Expression>().unwrap_err().position, 3);
+        assert_eq!("(1".parse::<Expression>().unwrap_err().position, 2);
+        assert!("1 2".parse::<Expression>().is_err());
+        assert!("0xZZ".parse::<Expression>().is_err());
+    }
+
+    #[test]
+    fn messages() {
+        let (mut cpu, bus) = system();
+        cpu.regs.a = 0x3C;
+
+        let message = Message::parse("A={A} SP={SP} diff={A - 100}", &SymbolTable::new()).unwrap();
+        assert_eq!(message.format(&cpu, &bus), "A=$3C SP=$FFFE diff=-40");
+        assert_eq!(message.to_string(), "A={A} SP={SP} diff={A - 100}");
+
+        assert_eq!(
+            Message::parse("x {A + }", &SymbolTable::new())
+                .unwrap_err()
+                .position,
+            7
+        );
+        assert!(Message::parse("{A", &SymbolTable::new()).is_err());
+    }
+}
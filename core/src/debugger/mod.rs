@@ -0,0 +1,535 @@
+pub mod call_stack;
+pub mod expression;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::bits::{get_bit, modify_bit};
+use crate::bus::MemoryBus;
+use crate::cpu::Cpu;
+use crate::interrupts::Interrupt;
+use crate::io;
+use crate::symbols::Symbol;
+
+use call_stack::StackImbalance;
+use expression::{Expression, Message};
+
+pub use crate::io::{register_address, register_name};
+
+/// Maximum number of messages logged by tracepoints kept until taken with [`Debugger::take_log`].
+const MAX_LOG_MESSAGES: usize = 1024;
+
+/// A kind of memory access made by the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Fetching the first byte of an instruction to execute.
+    Execute,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "read"),
+            Access::Write => write!(f, "write"),
+            Access::Execute => write!(f, "execute"),
+        }
+    }
+}
+
+/// What to do when a breakpoint or watchpoint is hit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HitAction {
+    /// Stop execution.
+    #[default]
+    Break,
+    /// Log a message (see [`Debugger::take_log`]) without stopping execution - i.e., act as a tracepoint.
+    Log(Message),
+}
+
+/// Stops execution before the instruction at the given address is executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    /// For addresses in cartridge ROM, only stop when this ROM bank is mapped (`None` to stop regardless of bank).
+    pub bank: Option<u16>,
+    /// Only hit the breakpoint when this expression evaluates to a non-zero value (`None` to always hit it).
+    pub condition: Option<Expression>,
+    pub action: HitAction,
+    /// Number of times the breakpoint has been hit (i.e., reached with its condition true).
+    pub hits: u64,
+}
+
+impl Breakpoint {
+    pub fn new(address: u16) -> Self {
+        Breakpoint {
+            address,
+            bank: None,
+            condition: None,
+            action: HitAction::Break,
+            hits: 0,
+        }
+    }
+
+    pub fn with_bank(address: u16, bank: u16) -> Self {
+        Breakpoint {
+            bank: Some(bank),
+            ..Breakpoint::new(address)
+        }
+    }
+
+    /// Stop at the address of the given symbol (only when its bank is mapped, should it be in switchable ROM).
+    pub fn at_symbol(symbol: &Symbol) -> Self {
+        match symbol.address {
+            0x4000..=0x7FFF => Breakpoint::with_bank(symbol.address, symbol.bank),
+            address => Breakpoint::new(address),
+        }
+    }
+
+    pub fn with_condition(self, condition: Expression) -> Self {
+        Breakpoint {
+            condition: Some(condition),
+            ..self
+        }
+    }
+
+    pub fn with_action(self, action: HitAction) -> Self {
+        Breakpoint { action, ..self }
+    }
+}
+
+/// Stops execution when the CPU accesses an address in the given range in the given way. Read and write watchpoints
+/// stop once the instruction making the access has finished executing while execute watchpoints stop before the
+/// instruction is executed (like a breakpoint).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<u16>,
+    pub access: Access,
+    /// Only stop when this value is read or written (or, for execute watchpoints, is the opcode executed).
+    pub value: Option<u8>,
+    /// Only hit the watchpoint when this expression evaluates to a non-zero value (`None` to always hit it). As with
+    /// stopping, the condition of a read or write watchpoint is evaluated once the instruction has finished executing.
+    pub condition: Option<Expression>,
+    pub action: HitAction,
+    /// Number of times the watchpoint has been hit (i.e., triggered with its condition true).
+    pub hits: u64,
+}
+
+impl Watchpoint {
+    pub fn new(range: RangeInclusive<u16>, access: Access) -> Self {
+        Watchpoint {
+            range,
+            access,
+            value: None,
+            condition: None,
+            action: HitAction::Break,
+            hits: 0,
+        }
+    }
+
+    /// Watch the IO register with the given name (e.g., "LCDC" or "IE"). Returns `None` if there is no such register.
+    pub fn io_register(name: &str, access: Access) -> Option<Self> {
+        io::register_address(name).map(|addr| Watchpoint::new(addr..=addr, access))
+    }
+
+    pub fn with_value(self, value: u8) -> Self {
+        Watchpoint {
+            value: Some(value),
+            ..self
+        }
+    }
+
+    pub fn with_condition(self, condition: Expression) -> Self {
+        Watchpoint {
+            condition: Some(condition),
+            ..self
+        }
+    }
+
+    pub fn with_action(self, action: HitAction) -> Self {
+        Watchpoint { action, ..self }
+    }
+
+    fn triggered_by(&self, addr: u16, access: Access, value: u8) -> bool {
+        self.access == access
+            && self.range.contains(&addr)
+            && self.value.is_none_or(|expected| expected == value)
+    }
+}
+
+/// What caused [`crate::GameBoy::run_until_break`] to return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakReason {
+    /// A breakpoint was reached at the given address.
+    Breakpoint(u16),
+    /// A watchpoint was triggered by the CPU accessing the given address.
+    Watchpoint {
+        address: u16,
+        access: Access,
+        value: u8,
+    },
+    /// The CPU dispatched an interrupt with a breakpoint set on it (PC is now the address of the interrupt handler).
+    Interrupt(Interrupt),
+    /// A return did not match the call stack (see [`Debugger::set_break_on_stack_imbalance`]). PC is now the address
+    /// returned to.
+    StackImbalance(StackImbalance),
+    /// The CPU locked up by executing an illegal opcode.
+    Locked,
+    /// The maximum number of cycles elapsed without anything else causing execution to stop.
+    CycleLimit,
+}
+
+impl fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BreakReason::Breakpoint(address) => write!(f, "breakpoint at {address:#06X}"),
+            BreakReason::Watchpoint {
+                address,
+                access,
+                value,
+            } => match io::register_name(*address) {
+                Some(name) => write!(f, "{access} watchpoint on {name} (value {value:#04X})"),
+                None => write!(
+                    f,
+                    "{access} watchpoint at {address:#06X} (value {value:#04X})"
+                ),
+            },
+            BreakReason::Interrupt(int) => write!(f, "{int}"),
+            BreakReason::StackImbalance(imbalance) => write!(f, "{imbalance}"),
+            BreakReason::Locked => write!(f, "CPU locked up"),
+            BreakReason::CycleLimit => write!(f, "cycle limit reached"),
+        }
+    }
+}
+
+/// A breakpoint or watchpoint (given by its index) that has been triggered but whose condition is yet to be evaluated.
+#[derive(Debug, Clone, Copy)]
+enum Hit {
+    Breakpoint(usize),
+    Watchpoint(usize, BreakReason),
+}
+
+/// Breakpoints and watchpoints that cause [`crate::GameBoy::run_until_break`] to stop. These have no effect on other
+/// methods of running the emulator such as [`crate::GameBoy::update`].
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    /// Interrupts on which to break, with a bit set for each as in the IE and IF registers.
+    interrupt_breakpoints: u8,
+    /// Whether to stop when a return does not match the call stack tracked by the CPU.
+    break_on_stack_imbalance: bool,
+    /// The first interrupt breakpoint or stack imbalance triggered since this was last cleared.
+    triggered: Option<BreakReason>,
+    /// Breakpoints and watchpoints triggered during the current step (each at most once).
+    hits: Vec<Hit>,
+    /// The PC at which [`crate::GameBoy::run_until_break`] last stopped due to a breakpoint or execute watchpoint
+    /// (i.e., before executing the instruction there), cleared once any instruction is executed.
+    resume_from: Option<u16>,
+    /// Messages logged by tracepoints.
+    log: VecDeque<String>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger::default()
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> Breakpoint {
+        self.hits.clear();
+        self.breakpoints.remove(index)
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, index: usize) -> Watchpoint {
+        self.hits.clear();
+        self.watchpoints.remove(index)
+    }
+
+    /// Set whether to stop when the given interrupt is dispatched.
+    pub fn set_interrupt_breakpoint(&mut self, int: Interrupt, enabled: bool) {
+        self.interrupt_breakpoints = modify_bit(self.interrupt_breakpoints, int.bit(), enabled);
+    }
+
+    pub fn interrupt_breakpoint(&self, int: Interrupt) -> bool {
+        get_bit(self.interrupt_breakpoints, int.bit())
+    }
+
+    /// Set whether to stop when a return does not match the call stack tracked by the CPU (see
+    /// [`crate::cpu::Cpu::call_stack`]) - e.g., when SP or the return address on the stack has been manipulated.
+    pub fn set_break_on_stack_imbalance(&mut self, enabled: bool) {
+        self.break_on_stack_imbalance = enabled;
+    }
+
+    pub fn break_on_stack_imbalance(&self) -> bool {
+        self.break_on_stack_imbalance
+    }
+
+    /// Remove all breakpoints and watchpoints.
+    pub fn clear(&mut self) {
+        *self = Debugger {
+            log: std::mem::take(&mut self.log),
+            ..Debugger::default()
+        };
+    }
+
+    /// Take the messages logged by tracepoints since this was last called (only the most recent messages are kept
+    /// should this not be called regularly).
+    pub fn take_log(&mut self) -> Vec<String> {
+        self.log.drain(..).collect()
+    }
+
+    /// Check for breakpoints or execute watchpoints on the instruction about to be executed at the given address (in
+    /// the given ROM bank, should the address be in cartridge ROM).
+    pub(crate) fn check_execute(&mut self, pc: u16, bank: u16, opcode: u8) {
+        let breakpoints = self
+            .breakpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, breakpoint)| {
+                breakpoint.address == pc
+                    && (pc >= 0x8000 || breakpoint.bank.is_none_or(|b| b == bank))
+            });
+        self.hits
+            .extend(breakpoints.map(|(index, _)| Hit::Breakpoint(index)));
+
+        self.watch(pc, Access::Execute, opcode);
+    }
+
+    /// Called for every read and write made by the CPU (excluding instruction fetches).
+    #[inline]
+    pub(crate) fn watch(&mut self, addr: u16, access: Access, value: u8) {
+        if self.watchpoints.is_empty() {
+            return;
+        }
+
+        for (index, watchpoint) in self.watchpoints.iter().enumerate() {
+            let already_hit = self
+                .hits
+                .iter()
+                .any(|hit| matches!(hit, Hit::Watchpoint(i, _) if *i == index));
+
+            if watchpoint.triggered_by(addr, access, value) && !already_hit {
+                let reason = BreakReason::Watchpoint {
+                    address: addr,
+                    access,
+                    value,
+                };
+                self.hits.push(Hit::Watchpoint(index, reason));
+            }
+        }
+    }
+
+    /// Called whenever the CPU dispatches an interrupt.
+    pub(crate) fn interrupt_dispatched(&mut self, int: Interrupt) {
+        if self.interrupt_breakpoint(int) {
+            self.triggered.get_or_insert(BreakReason::Interrupt(int));
+        }
+    }
+
+    /// Called whenever the CPU returns in a way that does not match its call stack.
+    pub(crate) fn stack_imbalance(&mut self, imbalance: StackImbalance) {
+        if self.break_on_stack_imbalance {
+            self.triggered
+                .get_or_insert(BreakReason::StackImbalance(imbalance));
+        }
+    }
+
+    /// Take the first interrupt breakpoint or stack imbalance triggered since this was last called.
+    pub(crate) fn take_triggered(&mut self) -> Option<BreakReason> {
+        self.triggered.take()
+    }
+
+    pub(crate) fn set_resume_from(&mut self, pc: u16) {
+        self.resume_from = Some(pc);
+    }
+
+    pub(crate) fn take_resume_from(&mut self) -> Option<u16> {
+        self.resume_from.take()
+    }
+
+    pub(crate) fn has_hits(&self) -> bool {
+        !self.hits.is_empty()
+    }
+
+    /// Forget any interrupt breakpoints, breakpoints, or watchpoints triggered.
+    pub(crate) fn clear_hits(&mut self) {
+        self.triggered = None;
+        self.hits.clear();
+    }
+
+    /// Evaluate the conditions of the breakpoints and watchpoints triggered since this was last called, counting hits
+    /// and logging the messages of tracepoints. Returns the reason for stopping should any that were hit have the
+    /// break action.
+    pub(crate) fn resolve_hits(&mut self, cpu: &Cpu, bus: &MemoryBus) -> Option<BreakReason> {
+        let mut reason = None;
+
+        for hit in std::mem::take(&mut self.hits) {
+            let (condition, action, hits, hit_reason) = match hit {
+                Hit::Breakpoint(index) => {
+                    let breakpoint = &mut self.breakpoints[index];
+                    (
+                        &breakpoint.condition,
+                        &breakpoint.action,
+                        &mut breakpoint.hits,
+                        BreakReason::Breakpoint(breakpoint.address),
+                    )
+                }
+                Hit::Watchpoint(index, hit_reason) => {
+                    let watchpoint = &mut self.watchpoints[index];
+                    (
+                        &watchpoint.condition,
+                        &watchpoint.action,
+                        &mut watchpoint.hits,
+                        hit_reason,
+                    )
+                }
+            };
+
+            if !condition
+                .as_ref()
+                .is_none_or(|condition| condition.is_true(cpu, bus))
+            {
+                continue;
+            }
+            *hits += 1;
+
+            match action {
+                HitAction::Break => {
+                    reason.get_or_insert(hit_reason);
+                }
+                HitAction::Log(message) => {
+                    let message = format!("{hit_reason}: {}", message.format(cpu, bus));
+                    log::info!("{message}");
+                    if self.log.len() == MAX_LOG_MESSAGES {
+                        self.log.pop_front();
+                    }
+                    self.log.push_back(message);
+                }
+            }
+        }
+
+        reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cartridge::Cartridge;
+    use crate::{mbc, Model};
+
+    fn resolve(d: &mut Debugger) -> Option<BreakReason> {
+        let mbc = mbc::from_cartridge(Cartridge::from_data(vec![0; 0x8000])).unwrap();
+        let bus = MemoryBus::new(mbc, Model::Dmg, Default::default());
+        d.resolve_hits(&Cpu::new(), &bus)
+    }
+
+    #[test]
+    fn breakpoint_banks() {
+        let mut d = Debugger::new();
+        d.add_breakpoint(Breakpoint::with_bank(0x4100, 2));
+        d.add_breakpoint(Breakpoint::new(0x0150));
+        d.add_breakpoint(Breakpoint::with_bank(0xC000, 5)); // bank ignored outside of ROM
+
+        d.check_execute(0x4100, 1, 0);
+        assert_eq!(resolve(&mut d), None);
+        d.check_execute(0x4100, 2, 0);
+        assert_eq!(resolve(&mut d), Some(BreakReason::Breakpoint(0x4100)));
+        d.check_execute(0x0150, 0, 0);
+        assert!(resolve(&mut d).is_some());
+        d.check_execute(0xC000, 1, 0);
+        assert!(resolve(&mut d).is_some());
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut d = Debugger::new();
+        d.add_watchpoint(Watchpoint::new(0xC000..=0xC0FF, Access::Write));
+        d.add_watchpoint(Watchpoint::io_register("lcdc", Access::Read).unwrap());
+        d.add_watchpoint(Watchpoint::new(0xD000..=0xD000, Access::Read).with_value(0x42));
+
+        d.watch(0xC000, Access::Read, 0);
+        d.watch(0xD000, Access::Read, 0x41);
+        assert_eq!(resolve(&mut d), None);
+
+        d.watch(0xC0FF, Access::Write, 1);
+        d.watch(0xFF40, Access::Read, 2); // only the first to stop is given
+        assert_eq!(
+            resolve(&mut d),
+            Some(BreakReason::Watchpoint {
+                address: 0xC0FF,
+                access: Access::Write,
+                value: 1
+            })
+        );
+
+        d.watch(0xD000, Access::Read, 0x42);
+        assert!(resolve(&mut d).is_some());
+
+        assert!(Watchpoint::io_register("nonsense", Access::Read).is_none());
+    }
+
+    #[test]
+    fn interrupt_breakpoints() {
+        let mut d = Debugger::new();
+        d.set_interrupt_breakpoint(Interrupt::Timer, true);
+        assert!(d.interrupt_breakpoint(Interrupt::Timer));
+
+        d.interrupt_dispatched(Interrupt::VBlank);
+        assert_eq!(d.take_triggered(), None);
+
+        d.interrupt_dispatched(Interrupt::Timer);
+        assert_eq!(
+            d.take_triggered(),
+            Some(BreakReason::Interrupt(Interrupt::Timer))
+        );
+    }
+
+    #[test]
+    fn conditions_and_tracepoints() {
+        let mut d = Debugger::new();
+        d.add_breakpoint(Breakpoint::new(0x0150).with_condition("A == 2".parse().unwrap()));
+        d.add_breakpoint(Breakpoint::new(0x0150).with_condition("A == 1".parse().unwrap()));
+        let message = Message::parse("SP={SP}", &Default::default()).unwrap();
+        d.add_watchpoint(
+            Watchpoint::new(0xC000..=0xC000, Access::Write).with_action(HitAction::Log(message)),
+        );
+
+        d.check_execute(0x0150, 0, 0);
+        d.watch(0xC000, Access::Write, 5);
+        d.watch(0xC000, Access::Write, 6); // each is hit at most once per step
+        assert_eq!(resolve(&mut d), Some(BreakReason::Breakpoint(0x0150))); // A is 1 at boot
+
+        let hits: Vec<u64> = d.breakpoints().iter().map(|b| b.hits).collect();
+        assert_eq!(hits, [0, 1]);
+        assert_eq!(d.watchpoints()[0].hits, 1);
+        assert_eq!(
+            d.take_log(),
+            ["write watchpoint at 0xC000 (value 0x05): SP=$FFFE"]
+        );
+        assert!(d.take_log().is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::gpu::oam::OAM_SIZE;
+
+/// Represents OAM DMA transfers, which copy 160 bytes from some source page in memory to OAM at a rate of one byte per
+/// M-cycle. Transfers are started by writing the upper byte of the source address to the DMA register (0xFF46).
+pub struct OamDma {
+    /// 0xFF46 - The value most recently written to the DMA register.
+    pub source: u8,
+    /// The most recent byte copied by the transfer, which is what the CPU reads should it try to access memory other
+    /// than HRAM while a transfer is in progress.
+    pub bus_value: u8,
+    /// Source page of a transfer that has been requested but is yet to begin due to the startup delay.
+    starting: Option<u8>,
+    transfer: Option<Transfer>,
+}
+
+#[derive(Clone, Copy)]
+struct Transfer {
+    source_address: u16,
+    /// Offset of the next byte to be copied.
+    offset: u16,
+}
+
+impl OamDma {
+    pub fn new() -> Self {
+        OamDma {
+            source: 0xFF,
+            bus_value: 0xFF,
+            starting: None,
+            transfer: None,
+        }
+    }
+
+    /// Request a new transfer from the given source page. The transfer begins after a single M-cycle delay (during
+    /// which any transfer already in progress continues).
+    pub fn start(&mut self, source: u8) {
+        self.source = source;
+        self.starting = Some(source);
+    }
+
+    /// Whether a transfer is in progress (in which case the CPU is restricted to accessing HRAM).
+    pub fn active(&self) -> bool {
+        self.transfer.is_some()
+    }
+
+    /// Advance by a single M-cycle. Returns the source address and OAM offset of the byte to be copied this M-cycle (if
+    /// a transfer is in progress).
+    pub fn cycle(&mut self) -> Option<(u16, u16)> {
+        let copy = self.transfer.as_mut().map(|t| {
+            let copy = (t.source_address + t.offset, t.offset);
+            t.offset += 1;
+            copy
+        });
+
+        if matches!(self.transfer, Some(t) if t.offset as usize >= OAM_SIZE) {
+            log::trace!("OAM DMA transfer complete");
+            self.transfer = None;
+        }
+
+        if let Some(source) = self.starting.take() {
+            log::trace!("starting OAM DMA transfer from page {source:#04X}");
+            self.transfer = Some(Transfer {
+                source_address: source_page_address(source),
+                offset: 0,
+            });
+        }
+
+        copy
+    }
+}
+
+impl Default for OamDma {
+    fn default() -> Self {
+        OamDma::new()
+    }
+}
+
+/// Get the address the transfer will copy from given the value written to the DMA register. Source pages from 0xE0 to
+/// 0xFF don't access echo RAM, OAM, or the IO registers but instead map onto work RAM at 0xC000 to 0xDFFF.
+fn source_page_address(source: u8) -> u16 {
+    let page = if source >= 0xE0 {
+        source - 0x20
+    } else {
+        source
+    };
+    page as u16 * 0x100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_timing() {
+        let mut dma = OamDma::new();
+        dma.start(0xC1);
+
+        // startup delay
+        assert_eq!(dma.cycle(), None);
+        assert!(dma.active());
+
+        for offset in 0..OAM_SIZE as u16 {
+            assert_eq!(dma.cycle(), Some((0xC100 + offset, offset)));
+        }
+
+        assert!(!dma.active());
+        assert_eq!(dma.cycle(), None);
+    }
+
+    #[test]
+    fn restart_transfer() {
+        let mut dma = OamDma::new();
+        dma.start(0x80);
+        dma.cycle();
+        dma.cycle();
+        dma.cycle();
+
+        // previous transfer continues during the startup delay of the new one
+        dma.start(0x90);
+        assert_eq!(dma.cycle(), Some((0x8002, 2)));
+        assert_eq!(dma.cycle(), Some((0x9000, 0)));
+        assert_eq!(dma.cycle(), Some((0x9001, 1)));
+    }
+
+    #[test]
+    fn high_source_pages() {
+        assert_eq!(source_page_address(0xDF), 0xDF00);
+        assert_eq!(source_page_address(0xE0), 0xC000);
+        assert_eq!(source_page_address(0xFE), 0xDE00);
+        assert_eq!(source_page_address(0xFF), 0xDF00);
+    }
+}
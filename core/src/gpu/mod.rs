@@ -8,19 +8,23 @@ use vram::{VideoRam, TILE_WIDTH};
 
 use crate::bits::{bit_accessors, get_bits, modify_bits};
 use crate::interrupts::{Interrupt, Interrupts};
-use crate::screen::{Screen, SCREEN_WIDTH};
-use crate::Cycles;
+use crate::palette::Layer;
+use crate::screen::{Screen, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::{Cycles, M_CYCLE};
 
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
-use self::oam::{Sprite, SPRITE_COUNT};
+use self::oam::{Sprite, OAM_ROW_COUNT, SPRITE_COUNT};
 
 const HBLANK_PERIOD: Cycles = 204;
 const VBLANK_PERIOD: Cycles = 456; // single line
 const SEARCHING_OAM_PERIOD: Cycles = 80;
 const TRANSFERRING_DATA_PERIOD: Cycles = 172;
 
+/// The final line of VBlank.
+const LAST_LINE: u8 = 153;
+
 pub struct Gpu {
     /// The screen to which the GPU will draw.
     pub screen: Screen,
@@ -124,13 +128,30 @@ impl Gpu {
         }
     }
 
+    pub fn in_vblank(&self) -> bool {
+        matches!(self.lcd_status.status(), LcdStatus::VBlank)
+    }
+
+    /// Get the row of OAM currently being read by the PPU. During the 'searching OAM' period, one row (2 sprites) is
+    /// read each M-cycle. Returns `None` outside of this period.
+    pub fn oam_row_being_read(&self) -> Option<usize> {
+        match self.lcd_status.status() {
+            LcdStatus::SearchingOAM if self.lcd_control.lcd_enable() => {
+                Some(((self.clock / M_CYCLE) as usize).min(OAM_ROW_COUNT - 1))
+            }
+            _ => None,
+        }
+    }
+
     /// Called after a scanline has been drawn. Increments the LCD Y position and, if we've reached the button of the
-    /// screen, will flag the VBlank interrupt and transition to the VBlank state. If however we are not yet at the
-    /// bottom of the screen, then we will move on to the next scanline by transitioning to the 'searching OAM' state.
+    /// screen, will make the completed frame visible, flag the VBlank interrupt, and transition to the VBlank state. If
+    /// however we are not yet at the bottom of the screen, then we will move on to the next scanline by transitioning
+    /// to the 'searching OAM' state.
     fn hblank(&mut self, interrupts: &mut Interrupts) -> LcdStatus {
         self.lcd_y += 1;
 
-        if self.lcd_y >= 143 {
+        if self.lcd_y >= SCREEN_HEIGHT as u8 {
+            self.screen.complete_frame();
             interrupts.flag(Interrupt::VBlank, true);
             LcdStatus::VBlank
         } else {
@@ -147,7 +168,7 @@ impl Gpu {
         self.window_y_trigger = false;
 
         // if 10 lines done since final HBlank (i.e., 10 * VBLANK_PERIOD ticks elapsed)
-        if self.lcd_y >= 153 {
+        if self.lcd_y > LAST_LINE {
             self.lcd_y = 0;
             return LcdStatus::SearchingOAM;
         }
@@ -253,6 +274,7 @@ impl Gpu {
                 draw_x.wrapping_add(horizontal_offset as u8),
                 self.lcd_y,
                 colour,
+                Layer::Background,
             );
         }
     }
@@ -293,10 +315,10 @@ impl Gpu {
             .vram
             .read_tile_line_unsigned_index(sprite.tile_index, sprite_line);
 
-        let palette = if sprite.use_palette_1 {
-            &self.obj_palette_1_data
+        let (palette, layer) = if sprite.use_palette_1 {
+            (&self.obj_palette_1_data, Layer::Object1)
         } else {
-            &self.obj_palette_0_data
+            (&self.obj_palette_0_data, Layer::Object0)
         };
 
         if sprite.x_flip {
@@ -310,6 +332,7 @@ impl Gpu {
                     sprite.x.saturating_add(horizontal_offset as u8) - 8,
                     self.lcd_y,
                     colour,
+                    layer,
                 );
             }
         }
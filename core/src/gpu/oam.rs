@@ -6,6 +6,19 @@ pub const OAM_SIZE: usize = (OAM_END - OAM_START + 1) as usize;
 
 pub const SPRITE_COUNT: u16 = 40;
 
+/// For the purposes of the OAM corruption bug, OAM is treated as 20 rows of 8 bytes (each row holding 2 sprites).
+pub const OAM_ROW_COUNT: usize = 20;
+const OAM_ROW_SIZE: usize = OAM_SIZE / OAM_ROW_COUNT;
+
+/// The kinds of CPU access that trigger the OAM corruption bug on DMG models.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OamCorruption {
+    Read,
+    Write,
+    /// A read that occurs in the same M-cycle as the address register is incremented/decremented (e.g. LD A, [HL+]).
+    ReadIncrease,
+}
+
 // The sprite attribute table (also known as OAM or object attribute memory) contains all sprite data.
 pub struct SpriteAttributeTable {
     data: [u8; OAM_SIZE],
@@ -28,6 +41,59 @@ impl SpriteAttributeTable {
         self.data[(addr - OAM_START) as usize] = value;
     }
 
+    /// Corrupt OAM given that the PPU was reading the specified row when the OAM corruption bug was triggered. The first
+    /// word (2 bytes) of the row is replaced by a bitwise combination of itself and words in the preceding row while the
+    /// remaining 3 words are copied from the preceding row. The first row is never corrupted.
+    pub fn corrupt(&mut self, row: usize, access: OamCorruption) {
+        debug_assert!(row < OAM_ROW_COUNT);
+
+        if row == 0 {
+            return;
+        }
+
+        // rows 4 to 18 are additionally affected by reads during increase in a way that corrupts the preceding row
+        if access == OamCorruption::ReadIncrease && (4..OAM_ROW_COUNT - 1).contains(&row) {
+            let current = row * OAM_ROW_SIZE;
+            let preceding = current - OAM_ROW_SIZE;
+            let two_before = preceding - OAM_ROW_SIZE;
+
+            for i in 0..2 {
+                let a = self.data[two_before + i];
+                let b = self.data[preceding + i];
+                let c = self.data[current + i];
+                let d = self.data[preceding + 4 + i];
+                self.data[preceding + i] = (b & (a | c | d)) | (a & c & d);
+            }
+
+            self.data.copy_within(preceding..current, two_before);
+            self.data.copy_within(preceding..current, current);
+        }
+
+        match access {
+            OamCorruption::Write => self.corrupt_row(row, |a, b, c| ((a ^ c) & (b ^ c)) ^ c),
+            OamCorruption::Read | OamCorruption::ReadIncrease => {
+                self.corrupt_row(row, |a, b, c| b | (a & c))
+            }
+        }
+    }
+
+    /// Replace the first word of a row with `glitch(first word of row, first word of preceding row, third word of
+    /// preceding row)` and copy the rest of the preceding row.
+    fn corrupt_row(&mut self, row: usize, glitch: impl Fn(u8, u8, u8) -> u8) {
+        let current = row * OAM_ROW_SIZE;
+        let preceding = current - OAM_ROW_SIZE;
+
+        for i in 0..2 {
+            self.data[current + i] = glitch(
+                self.data[current + i],
+                self.data[preceding + i],
+                self.data[preceding + 4 + i],
+            );
+        }
+
+        self.data.copy_within(preceding + 2..current, current + 2);
+    }
+
     /// Read the sprite data of the sprite at the given index in the table.
     pub fn read_sprite(&self, index: u16) -> Sprite {
         debug_assert!(index < SPRITE_COUNT);
@@ -60,3 +126,75 @@ pub struct Sprite {
     pub x_flip: bool,
     pub use_palette_1: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> SpriteAttributeTable {
+        let mut oam = SpriteAttributeTable::new();
+        for (i, b) in oam.data.iter_mut().enumerate() {
+            *b = (i as u8).wrapping_mul(37) ^ 0x5A;
+        }
+        oam
+    }
+
+    #[test]
+    fn corrupt_first_row() {
+        let mut oam = table();
+        oam.corrupt(0, OamCorruption::Write);
+        assert_eq!(oam.data, table().data);
+    }
+
+    #[test]
+    fn corrupt_write() {
+        let before = table().data;
+        let mut oam = table();
+        oam.corrupt(2, OamCorruption::Write);
+
+        // ((a ^ c) & (b ^ c)) ^ c where a = current row, b and c = first and third words of preceding row
+        for i in 0..2 {
+            let (a, b, c) = (before[16 + i], before[8 + i], before[12 + i]);
+            assert_eq!(oam.data[16 + i], ((a ^ c) & (b ^ c)) ^ c);
+        }
+        assert_eq!(oam.data[18..24], before[10..16]);
+        assert_eq!(oam.data[24..], before[24..]);
+    }
+
+    #[test]
+    fn corrupt_read() {
+        let before = table().data;
+        let mut oam = table();
+        oam.corrupt(2, OamCorruption::Read);
+
+        // b | (a & c)
+        for i in 0..2 {
+            let (a, b, c) = (before[16 + i], before[8 + i], before[12 + i]);
+            assert_eq!(oam.data[16 + i], b | (a & c));
+        }
+        assert_eq!(oam.data[18..24], before[10..16]);
+    }
+
+    #[test]
+    fn corrupt_read_increase() {
+        let before = table().data;
+        let mut oam = table();
+        oam.corrupt(5, OamCorruption::ReadIncrease);
+
+        // preceding row is corrupted then copied to the current row and the row two before
+        for i in 0..2 {
+            let (a, b, c, d) = (
+                before[24 + i],
+                before[32 + i],
+                before[40 + i],
+                before[36 + i],
+            );
+            let corrupted = (b & (a | c | d)) | (a & c & d);
+            assert_eq!(oam.data[24 + i], corrupted);
+            assert_eq!(oam.data[32 + i], corrupted);
+        }
+        for row in [24, 32, 40] {
+            assert_eq!(oam.data[row + 2..row + 8], before[34..40]);
+        }
+    }
+}
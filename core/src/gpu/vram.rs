@@ -30,6 +30,10 @@ impl VideoRam {
         self.data[(addr - VRAM_START) as usize] = value;
     }
 
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
     /// Read a tile at the given index in the memory area 0x8000 to 0x87FF using an unsigned offset.
     pub fn read_tile_line_unsigned_index(&self, offset: u8, line: u8) -> [u8; TILE_WIDTH] {
         debug_assert!((line as usize) < TILE_WIDTH * 2);
@@ -48,7 +48,7 @@ impl Default for Interrupts {
     }
 }
 
-#[derive(Debug, Copy, Clone, FromPrimitive)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 pub enum Interrupt {
     VBlank,
     LcdStat,
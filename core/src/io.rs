@@ -0,0 +1,203 @@
+use crate::Model;
+
+pub const IO_START: u16 = 0xFF00;
+pub const IO_END: u16 = 0xFF7F;
+
+pub const AUDIO_START: u16 = 0xFF10;
+pub const AUDIO_END: u16 = 0xFF3F;
+pub const AUDIO_SIZE: usize = (AUDIO_END - AUDIO_START + 1) as usize;
+
+/// The bits of an IO register that can be read and written. Bits that cannot be read (either because they are unused or
+/// are write-only) always read as 1 while bits that cannot be written retain their value when the register is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoRegisterMask {
+    pub readable: u8,
+    pub writable: u8,
+}
+
+impl IoRegisterMask {
+    const fn new(readable: u8, writable: u8) -> Self {
+        IoRegisterMask { readable, writable }
+    }
+
+    /// Apply this mask to the stored value of the register to get the value the CPU reads.
+    pub fn read(&self, stored: u8) -> u8 {
+        stored | !self.readable
+    }
+
+    /// Combine a value written by the CPU with the stored value of the register.
+    pub fn write(&self, stored: u8, value: u8) -> u8 {
+        (stored & !self.writable) | (value & self.writable)
+    }
+}
+
+const READ_WRITE: IoRegisterMask = IoRegisterMask::new(0xFF, 0xFF);
+const READ_ONLY: IoRegisterMask = IoRegisterMask::new(0xFF, 0x00);
+const WRITE_ONLY: IoRegisterMask = IoRegisterMask::new(0x00, 0xFF);
+
+/// Get the mask of the IO register at the given address (from 0xFF00 to 0xFF7F). Returns `None` for addresses that
+/// don't map to any register on the given model (which read as 0xFF and ignore writes).
+pub fn io_register_mask(addr: u16, model: Model) -> Option<IoRegisterMask> {
+    debug_assert!((IO_START..=IO_END).contains(&addr));
+
+    let cgb = model == Model::Cgb;
+
+    let mask = match addr {
+        0xFF00 => IoRegisterMask::new(0x3F, 0x30),        // P1
+        0xFF01 => READ_WRITE,                             // SB
+        0xFF02 if cgb => IoRegisterMask::new(0x83, 0x83), // SC
+        0xFF02 => IoRegisterMask::new(0x81, 0x81),        // SC
+        0xFF04..=0xFF06 => READ_WRITE,                    // DIV, TIMA, TMA
+        0xFF07 => IoRegisterMask::new(0x07, 0x07),        // TAC
+        0xFF0F => IoRegisterMask::new(0x1F, 0x1F),        // IF
+
+        0xFF10 => IoRegisterMask::new(0x7F, 0x7F), // NR10
+        0xFF11 => IoRegisterMask::new(0xC0, 0xFF), // NR11
+        0xFF12 => READ_WRITE,                      // NR12
+        0xFF13 => WRITE_ONLY,                      // NR13
+        0xFF14 => IoRegisterMask::new(0x40, 0xC7), // NR14
+        0xFF16 => IoRegisterMask::new(0xC0, 0xFF), // NR21
+        0xFF17 => READ_WRITE,                      // NR22
+        0xFF18 => WRITE_ONLY,                      // NR23
+        0xFF19 => IoRegisterMask::new(0x40, 0xC7), // NR24
+        0xFF1A => IoRegisterMask::new(0x80, 0x80), // NR30
+        0xFF1B => WRITE_ONLY,                      // NR31
+        0xFF1C => IoRegisterMask::new(0x60, 0x60), // NR32
+        0xFF1D => WRITE_ONLY,                      // NR33
+        0xFF1E => IoRegisterMask::new(0x40, 0xC7), // NR34
+        0xFF20 => IoRegisterMask::new(0x00, 0x3F), // NR41
+        0xFF21 | 0xFF22 => READ_WRITE,             // NR42, NR43
+        0xFF23 => IoRegisterMask::new(0x40, 0xC0), // NR44
+        0xFF24 | 0xFF25 => READ_WRITE,             // NR50, NR51
+        0xFF26 => IoRegisterMask::new(0x8F, 0x80), // NR52
+        0xFF30..=0xFF3F => READ_WRITE,             // wave RAM
+
+        0xFF40 => READ_WRITE,                             // LCDC
+        0xFF41 => IoRegisterMask::new(0x7F, 0x78),        // STAT
+        0xFF42 | 0xFF43 => READ_WRITE,                    // SCY, SCX
+        0xFF44 => READ_ONLY,                              // LY
+        0xFF45..=0xFF4B => READ_WRITE,                    // LYC, DMA, BGP, OBP0, OBP1, WY, WX
+        0xFF4D if cgb => IoRegisterMask::new(0x81, 0x01), // KEY1
+
+        _ => return None,
+    };
+
+    Some(mask)
+}
+
+/// Names of the IO registers (and the IE register) as given by Pan Docs.
+const REGISTER_NAMES: [(u16, &str); 55] = [
+    (0xFF00, "P1"),
+    (0xFF01, "SB"),
+    (0xFF02, "SC"),
+    (0xFF04, "DIV"),
+    (0xFF05, "TIMA"),
+    (0xFF06, "TMA"),
+    (0xFF07, "TAC"),
+    (0xFF0F, "IF"),
+    (0xFF10, "NR10"),
+    (0xFF11, "NR11"),
+    (0xFF12, "NR12"),
+    (0xFF13, "NR13"),
+    (0xFF14, "NR14"),
+    (0xFF16, "NR21"),
+    (0xFF17, "NR22"),
+    (0xFF18, "NR23"),
+    (0xFF19, "NR24"),
+    (0xFF1A, "NR30"),
+    (0xFF1B, "NR31"),
+    (0xFF1C, "NR32"),
+    (0xFF1D, "NR33"),
+    (0xFF1E, "NR34"),
+    (0xFF20, "NR41"),
+    (0xFF21, "NR42"),
+    (0xFF22, "NR43"),
+    (0xFF23, "NR44"),
+    (0xFF24, "NR50"),
+    (0xFF25, "NR51"),
+    (0xFF26, "NR52"),
+    (0xFF40, "LCDC"),
+    (0xFF41, "STAT"),
+    (0xFF42, "SCY"),
+    (0xFF43, "SCX"),
+    (0xFF44, "LY"),
+    (0xFF45, "LYC"),
+    (0xFF46, "DMA"),
+    (0xFF47, "BGP"),
+    (0xFF48, "OBP0"),
+    (0xFF49, "OBP1"),
+    (0xFF4A, "WY"),
+    (0xFF4B, "WX"),
+    (0xFF4D, "KEY1"),
+    (0xFF4F, "VBK"),
+    (0xFF51, "HDMA1"),
+    (0xFF52, "HDMA2"),
+    (0xFF53, "HDMA3"),
+    (0xFF54, "HDMA4"),
+    (0xFF55, "HDMA5"),
+    (0xFF56, "RP"),
+    (0xFF68, "BCPS"),
+    (0xFF69, "BCPD"),
+    (0xFF6A, "OCPS"),
+    (0xFF6B, "OCPD"),
+    (0xFF70, "SVBK"),
+    (0xFFFF, "IE"),
+];
+
+/// Get the name of the IO register (or IE register) at the given address.
+pub fn register_name(addr: u16) -> Option<&'static str> {
+    REGISTER_NAMES
+        .iter()
+        .find(|(register, _)| *register == addr)
+        .map(|(_, name)| *name)
+}
+
+/// Get the address of the IO register (or IE register) with the given name (case insensitive).
+pub fn register_address(name: &str) -> Option<u16> {
+    REGISTER_NAMES
+        .iter()
+        .find(|(_, register)| register.eq_ignore_ascii_case(name))
+        .map(|(addr, _)| *addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let stat = io_register_mask(0xFF41, Model::Dmg).unwrap();
+        assert_eq!(stat.read(0b0000_0101), 0b1000_0101);
+
+        let nr13 = io_register_mask(0xFF13, Model::Dmg).unwrap();
+        assert_eq!(nr13.read(0x12), 0xFF);
+    }
+
+    #[test]
+    fn write() {
+        let stat = io_register_mask(0xFF41, Model::Dmg).unwrap();
+        assert_eq!(stat.write(0b0000_0110, 0xFF), 0b0111_1110);
+
+        let ly = io_register_mask(0xFF44, Model::Dmg).unwrap();
+        assert_eq!(ly.write(0x90, 0x12), 0x90);
+    }
+
+    #[test]
+    fn unmapped() {
+        for addr in [0xFF03, 0xFF08, 0xFF15, 0xFF1F, 0xFF27, 0xFF4C, 0xFF7F] {
+            assert_eq!(io_register_mask(addr, Model::Dmg), None);
+        }
+
+        assert_eq!(io_register_mask(0xFF4D, Model::Dmg), None);
+        assert!(io_register_mask(0xFF4D, Model::Cgb).is_some());
+    }
+
+    #[test]
+    fn register_names() {
+        assert_eq!(register_name(0xFF40), Some("LCDC"));
+        assert_eq!(register_name(0xFF03), None);
+        assert_eq!(register_address("stat"), Some(0xFF41));
+        assert_eq!(register_address("IE"), Some(0xFFFF));
+        assert_eq!(register_address("XYZ"), None);
+    }
+}
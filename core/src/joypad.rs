@@ -1,7 +1,19 @@
-use crate::bits::{get_bit, modify_bit};
+use crate::bits::{get_bit, get_bits, modify_bit};
+use crate::interrupts::{Interrupt, Interrupts};
+use crate::sgb::{Packet, PacketReceiver};
 
 const BUTTON_COUNT: usize = 8;
 
+/// Maximum number of joypads that can be connected (via the Super Game Boy).
+pub const MAX_PLAYERS: usize = 4;
+
+/// Bits 4 and 5 of the P1 register are used to select the direction and action buttons respectively.
+const SELECT_MASK: u8 = 0b0011_0000;
+
+/// Input lines when neither group of buttons is selected on the first joypad. On the Super Game Boy, the ID of the
+/// current joypad is subtracted from this.
+const JOYPAD_ID_BASE: u8 = 0xF;
+
 #[derive(Clone, Copy)]
 pub enum Button {
     Start,
@@ -14,69 +26,157 @@ pub enum Button {
     B,
 }
 
-#[derive(Debug, PartialEq)]
-enum SelectedButtons {
-    ActionButtons,
-    DirectionButtons,
-    None,
-}
-
 const ACTION_BUTTONS: &[Button] = &[Button::A, Button::B, Button::Select, Button::Start];
 const DIRECTION_BUTTONS: &[Button] = &[Button::Right, Button::Left, Button::Up, Button::Down];
 
 pub struct Joypad {
-    buttons: [bool; BUTTON_COUNT],
-    selected: SelectedButtons,
+    /// State of the buttons of each joypad.
+    buttons: [[bool; BUTTON_COUNT]; MAX_PLAYERS],
+    /// Number of joypads being read (1, 2, or 4), requested by a Super Game Boy game with MLT_REQ.
+    players: usize,
+    /// The joypad whose buttons are currently reported through P1. Advances each time both select bits are returned
+    /// high when multiple players are enabled.
+    current_player: usize,
+    /// The select bits (4 and 5) of the P1 register. Note that a group of buttons is selected when its bit is 0.
+    select: u8,
+    /// Set when one of the input lines transitions from high to low, cleared once the joypad interrupt is flagged.
+    interrupt_pending: bool,
+    /// Receives packets sent through P1 when running on a Super Game Boy.
+    sgb_packets: Option<PacketReceiver>,
 }
 
 impl Joypad {
     pub fn new() -> Self {
         Joypad {
-            buttons: [false; BUTTON_COUNT],
-            selected: SelectedButtons::None,
+            buttons: [[false; BUTTON_COUNT]; MAX_PLAYERS],
+            players: 1,
+            current_player: 0,
+            select: SELECT_MASK,
+            interrupt_pending: false,
+            sgb_packets: None,
+        }
+    }
+
+    /// Create a joypad that also receives the packets games send to the Super Game Boy through P1.
+    pub fn with_sgb() -> Self {
+        Joypad {
+            sgb_packets: Some(PacketReceiver::new()),
+            ..Joypad::new()
         }
     }
 
+    /// Reset the P1 register to its power-on state. The state of the buttons is kept as they are physically held.
+    pub fn reset(&mut self) {
+        self.select = SELECT_MASK;
+        self.interrupt_pending = false;
+        self.players = 1;
+        self.current_player = 0;
+        if let Some(packets) = &mut self.sgb_packets {
+            *packets = PacketReceiver::new();
+        }
+    }
+
+    /// Flag the joypad interrupt if any of the input lines went from high to low since the last update.
+    pub fn update(&mut self, interrupts: &mut Interrupts) {
+        if self.interrupt_pending {
+            log::trace!("joypad input line went low so flagging joypad interrupt");
+            interrupts.flag(Interrupt::Joypad, true);
+            self.interrupt_pending = false;
+        }
+    }
+
+    /// Get the value of the P1 register. Bits 6 and 7 are unused and always read as 1.
     pub fn get_byte(&self) -> u8 {
-        let mut b;
+        0b1100_0000 | self.select | self.input_lines()
+    }
 
-        let selected_buttons = match self.selected {
-            SelectedButtons::ActionButtons => {
-                b = modify_bit(0, 4, true); // set direction buttons bit
-                ACTION_BUTTONS
-            }
+    pub fn set_byte(&mut self, b: u8) {
+        self.update_input_lines(|j| {
+            let select = b & SELECT_MASK;
 
-            SelectedButtons::DirectionButtons => {
-                b = modify_bit(0, 5, true); // set action buttons bit
-                DIRECTION_BUTTONS
+            // returning both select bits high moves on to the next joypad
+            if select == SELECT_MASK && j.select != SELECT_MASK {
+                j.current_player = (j.current_player + 1) % j.players;
             }
 
-            SelectedButtons::None => return 0,
-        };
+            j.select = select;
+        });
 
-        for (bit, button) in selected_buttons.iter().enumerate() {
-            b = modify_bit(b, bit as u8, !self.get_button(*button));
+        if let Some(packets) = &mut self.sgb_packets {
+            packets.write_select(self.select);
         }
+    }
 
-        b
+    /// Take the most recently received Super Game Boy packet, should there be one.
+    pub fn take_sgb_packet(&mut self) -> Option<Packet> {
+        self.sgb_packets.as_mut()?.take_packet()
     }
 
-    pub fn set_byte(&mut self, b: u8) {
-        self.selected = if !get_bit(b, 5) {
-            SelectedButtons::ActionButtons
-        } else if !get_bit(b, 4) {
-            SelectedButtons::DirectionButtons
-        } else {
-            SelectedButtons::None
-        };
+    /// Number of joypads being read (1, 2, or 4).
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    /// Set the number of joypads being read (as requested by MLT_REQ), starting again from the first joypad.
+    pub fn set_players(&mut self, players: usize) {
+        debug_assert!(matches!(players, 1 | 2 | 4));
+        self.update_input_lines(|j| {
+            j.players = players;
+            j.current_player = 0;
+        });
+    }
+
+    /// Get the state of the four input lines (the lower nibble of P1). A line is low (0) when a button in any of the
+    /// selected groups that is connected to that line is pressed. When both groups are selected, the lines of both
+    /// groups are combined. When neither group is selected, the lines give the ID of the current joypad.
+    pub fn input_lines(&self) -> u8 {
+        if self.select == SELECT_MASK {
+            return JOYPAD_ID_BASE - self.current_player as u8;
+        }
+
+        let mut lines = 0xF;
+
+        if !get_bit(self.select, 5) {
+            lines &= self.group_lines(ACTION_BUTTONS);
+        }
+
+        if !get_bit(self.select, 4) {
+            lines &= self.group_lines(DIRECTION_BUTTONS);
+        }
+
+        lines
+    }
+
+    fn group_lines(&self, group: &[Button]) -> u8 {
+        group.iter().enumerate().fold(0xF, |lines, (bit, button)| {
+            modify_bit(lines, bit as u8, !self.get_button(*button))
+        })
     }
 
     fn get_button(&self, button: Button) -> bool {
-        self.buttons[button as usize]
+        self.buttons[self.current_player][button as usize]
     }
 
+    /// Set the state of a button on the first joypad.
     pub fn set_button(&mut self, button: Button, value: bool) {
-        self.buttons[button as usize] = value;
+        self.set_player_button(0, button, value);
+    }
+
+    /// Set the state of a button on the joypad of the given player (from 0 to 3).
+    pub fn set_player_button(&mut self, player: usize, button: Button, value: bool) {
+        assert!(player < MAX_PLAYERS, "invalid player {player}");
+        self.update_input_lines(|j| j.buttons[player][button as usize] = value);
+    }
+
+    /// Apply some change to the joypad state and request an interrupt if it causes any input line to go low.
+    fn update_input_lines(&mut self, f: impl FnOnce(&mut Self)) {
+        let before = self.input_lines();
+        f(self);
+        let after = self.input_lines();
+
+        if get_bits(before & !after, 0, 4) != 0 {
+            self.interrupt_pending = true;
+        }
     }
 }
 
@@ -93,25 +193,113 @@ mod tests {
     #[test]
     fn get() {
         let mut j = Joypad::new();
+        assert_eq!(j.get_byte(), 0xFF); // nothing selected
 
-        j.selected = SelectedButtons::ActionButtons;
+        j.set_byte(0b010000); // select action buttons
         j.set_button(Button::A, true);
         j.set_button(Button::Select, true);
-        assert_eq!(j.get_byte(), 0b11010);
+        assert_eq!(j.get_byte(), 0b11011010);
 
-        j.selected = SelectedButtons::DirectionButtons;
-        assert_eq!(j.get_byte(), 0b101111);
+        j.set_byte(0b100000); // select direction buttons
+        assert_eq!(j.get_byte(), 0b11101111);
         j.set_button(Button::Up, true);
         j.set_button(Button::Down, true);
-        assert_eq!(j.get_byte(), 0b100011);
+        assert_eq!(j.get_byte(), 0b11100011);
+
+        j.set_byte(0); // select both groups
+        assert_eq!(j.get_byte(), 0b11000010);
+
+        j.set_byte(0b110000); // select neither group
+        assert_eq!(j.get_byte(), 0xFF);
     }
 
     #[test]
     fn set() {
         let mut j = Joypad::new();
         j.set_byte(0b100000);
-        assert_eq!(j.selected, SelectedButtons::DirectionButtons);
+        assert_eq!(j.select, 0b100000);
+        j.set_byte(0b011111);
+        assert_eq!(j.select, 0b010000);
+    }
+
+    #[test]
+    fn sgb_packet() {
+        let packet = [0x42; crate::sgb::PACKET_SIZE];
+
+        let mut j = Joypad::new();
+        crate::sgb::send_packet(&packet, |select| j.set_byte(select));
+        assert_eq!(j.take_sgb_packet(), None);
+
+        let mut j = Joypad::with_sgb();
+        crate::sgb::send_packet(&packet, |select| j.set_byte(select));
+        assert_eq!(j.take_sgb_packet(), Some(packet));
+    }
+
+    #[test]
+    fn multiplayer() {
+        let mut j = Joypad::with_sgb();
+        j.set_player_button(1, Button::A, true);
+
+        // only the first joypad is read until multiple players are requested
+        j.set_byte(0b100000);
+        j.set_byte(0b110000);
+        assert_eq!(j.get_byte(), 0xFF);
+
+        j.set_players(2);
+        assert_eq!(j.get_byte(), 0xFF);
         j.set_byte(0b010000);
-        assert_eq!(j.selected, SelectedButtons::ActionButtons);
+        assert_eq!(j.get_byte(), 0b11011111);
+
+        // returning both select bits high advances to the second joypad
+        j.set_byte(0b110000);
+        assert_eq!(j.get_byte(), 0xFE);
+        j.set_byte(0b010000);
+        assert_eq!(j.get_byte(), 0b11011110);
+
+        // and then wraps around to the first
+        j.set_byte(0b110000);
+        assert_eq!(j.get_byte(), 0xFF);
+
+        j.set_players(4);
+        for id in [0xF, 0xE, 0xD, 0xC, 0xF] {
+            assert_eq!(j.get_byte() & 0xF, id);
+            j.set_byte(0b100000);
+            j.set_byte(0b110000);
+        }
+    }
+
+    #[test]
+    fn interrupt() {
+        let mut j = Joypad::new();
+        let mut ints = Interrupts::new();
+        ints.flag = 0;
+
+        // no interrupt when the button's group isn't selected
+        j.set_button(Button::Start, true);
+        j.update(&mut ints);
+        assert!(!ints.is_flagged(Interrupt::Joypad));
+
+        // selecting the group pulls the line low
+        j.set_byte(0b010000);
+        j.update(&mut ints);
+        assert!(ints.is_flagged(Interrupt::Joypad));
+        ints.flag(Interrupt::Joypad, false);
+
+        // releasing a button (low-to-high transition) doesn't trigger an interrupt
+        j.set_button(Button::Start, false);
+        j.update(&mut ints);
+        assert!(!ints.is_flagged(Interrupt::Joypad));
+
+        // pressing a button in the selected group does
+        j.set_button(Button::B, true);
+        j.update(&mut ints);
+        assert!(ints.is_flagged(Interrupt::Joypad));
+        ints.flag(Interrupt::Joypad, false);
+
+        // no interrupt when the line is already low due to another button
+        j.set_byte(0);
+        j.set_button(Button::Left, true);
+        j.update(&mut ints);
+        assert!(!ints.is_flagged(Interrupt::Joypad));
     }
 }
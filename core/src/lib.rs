@@ -1,60 +1,259 @@
 #[cfg(test)]
 mod tests;
 
+pub mod archive;
 mod bits;
 pub mod bus;
 pub mod cartridge;
+pub mod cheats;
+pub mod colourisation;
 pub mod cpu;
+pub mod debugger;
+mod dma;
 mod gpu;
-mod interrupts;
+pub mod interrupts;
+mod io;
 pub mod joypad;
 pub mod mbc;
+pub mod palette;
+pub mod parse;
+pub mod patch;
+pub mod ram_init;
 pub mod screen;
 mod serial;
+pub mod sgb;
+mod speed;
+pub mod symbols;
 mod timer;
 
 use bus::MemoryBus;
 use cpu::Cpu;
+use debugger::BreakReason;
 use mbc::MemoryBankController;
+use ram_init::RamInit;
 
 /// Type to represent some number of cycles. Note that this emulator exclusively uses T-Cycles
 /// rather than M-Cycles or any mixing of two.
 /// 1 M-Cycle = 4 T-Cycles
 type Cycles = u32;
 
+/// Number of T-cycles in a single M-cycle. Each memory access performed by the CPU takes exactly one M-cycle.
+const M_CYCLE: Cycles = 4;
+
 const CYCLES_PER_SECOND: Cycles = 4194304;
 
+/// Number of T-cycles taken by the PPU to draw a single frame (154 lines of 456 cycles each).
+pub const CYCLES_PER_FRAME: Cycles = 70224;
+
+/// The Game Boy hardware model being emulated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Model {
+    /// The original Game Boy.
+    #[default]
+    Dmg,
+    /// The Super Game Boy. Packets sent by the game through the P1 register are processed to colourise the screen and
+    /// display a border around it (see [`sgb::Sgb`]).
+    Sgb,
+    /// The Game Boy Color. Note that only some CGB hardware features are emulated (currently just double speed mode).
+    Cgb,
+}
+
+/// Options used when constructing a [`GameBoy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    /// The hardware model to emulate.
+    pub model: Model,
+    /// The policy for the contents of RAM at power on.
+    pub ram_init: RamInit,
+}
+
 /// Game Boy console emulator.
 pub struct GameBoy {
     pub cpu: Cpu,
     pub bus: MemoryBus,
+    options: Options,
+    /// Cycles executed beyond the target of the last call to [`GameBoy::run_cycles`] (as instructions can't be split).
+    overshoot: Cycles,
+    /// Fraction of a cycle left over from the last call to [`GameBoy::update`].
+    fractional_cycles: f64,
 }
 
 impl GameBoy {
     pub fn new(mbc: Box<dyn MemoryBankController>) -> Self {
+        GameBoy::with_model(mbc, Model::default())
+    }
+
+    pub fn with_model(mbc: Box<dyn MemoryBankController>, model: Model) -> Self {
+        GameBoy::with_options(
+            mbc,
+            Options {
+                model,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn with_options(mbc: Box<dyn MemoryBankController>, options: Options) -> Self {
         GameBoy {
             cpu: Cpu::new(),
-            bus: MemoryBus::new(mbc),
+            bus: MemoryBus::new(mbc, options.model, options.ram_init),
+            options,
+            overshoot: 0,
+            fractional_cycles: 0.0,
         }
     }
 
+    /// Reset the console to its power-on state without losing the contents of cartridge RAM (as if the console were
+    /// switched off and on again).
+    pub fn reset(&mut self) {
+        self.cpu = Cpu::new();
+        self.bus.reset(self.options.ram_init);
+        self.overshoot = 0;
+        self.fractional_cycles = 0.0;
+    }
+
+    /// Insert a different cartridge and reset the console. Returns the previously inserted cartridge (so that the
+    /// contents of its RAM can be saved, for example).
+    pub fn swap_cartridge(
+        &mut self,
+        mbc: Box<dyn MemoryBankController>,
+    ) -> Box<dyn MemoryBankController> {
+        let previous = self.bus.swap_cartridge(mbc, self.options.ram_init);
+        self.reset();
+        previous
+    }
+
     /// Update the state of the console - fetch and execute CPU instructions, handle interrupts, update the timer,
     /// handle rendering, etc. The `delta` parameter must express in seconds how long has passed since the last update.
     pub fn update(&mut self, delta: f32) {
-        let total_cycles_this_update = (delta * CYCLES_PER_SECOND as f32) as Cycles;
-        let mut cycles_so_far = 0;
+        let cycles = self.cycles_in(delta);
+        self.run_cycles(cycles);
+    }
+
+    /// Run for the given number of cycles. As instructions can't be split, the last instruction executed may overshoot
+    /// the target - those extra cycles are deducted from the next call. Returns the number of cycles actually executed.
+    pub fn run_cycles(&mut self, cycles: Cycles) -> Cycles {
+        if self.overshoot >= cycles {
+            self.overshoot -= cycles;
+            return 0;
+        }
+
+        let target = cycles - self.overshoot;
+        let mut elapsed = 0;
 
-        while cycles_so_far < total_cycles_this_update {
-            let cycles = self.step();
-            cycles_so_far += cycles;
+        while elapsed < target {
+            elapsed += self.step();
         }
+
+        self.overshoot = elapsed - target;
+        elapsed
+    }
+
+    /// Run until the PPU enters VBlank (i.e., a frame has been drawn). Returns the number of cycles elapsed. Should the
+    /// PPU not enter VBlank (such as when the system clock is stopped), this returns after a frame's worth of cycles.
+    pub fn run_frame(&mut self) -> Cycles {
+        let max_cycles = if self.bus.double_speed() {
+            CYCLES_PER_FRAME * 2
+        } else {
+            CYCLES_PER_FRAME
+        };
+
+        let mut elapsed = 0;
+
+        while elapsed < max_cycles {
+            let was_in_vblank = self.bus.gpu.in_vblank();
+            elapsed += self.step();
+
+            if !was_in_vblank && self.bus.gpu.in_vblank() {
+                break;
+            }
+        }
+
+        elapsed
+    }
+
+    /// Run until a breakpoint, watchpoint, or interrupt breakpoint set on [`MemoryBus::debugger`] is triggered, or the
+    /// given number of cycles elapse. Breakpoints (and execute watchpoints) are checked before the instruction at them
+    /// is executed. Should execution have stopped at one and not moved since, it isn't triggered again at the current
+    /// PC so that execution can be resumed.
+    pub fn run_until_break(&mut self, max_cycles: Cycles) -> BreakReason {
+        self.bus.debugger.clear_hits();
+        let mut resume_from = self.bus.debugger.take_resume_from();
+
+        let mut elapsed = 0;
+
+        while elapsed < max_cycles {
+            if self.cpu.state() == cpu::State::Running {
+                let pc = self.cpu.regs.pc;
+
+                if resume_from.take() != Some(pc) {
+                    let opcode = self.bus.read8(pc);
+                    let bank = self.bus.rom_bank(pc);
+                    self.bus.debugger.check_execute(pc, bank, opcode);
+
+                    if let Some(reason) = self.resolve_hits() {
+                        self.bus.debugger.set_resume_from(pc);
+                        return reason;
+                    }
+                }
+            }
+
+            elapsed += self.step();
+
+            if let Some(reason) = self.bus.debugger.take_triggered() {
+                return reason;
+            }
+
+            if let Some(reason) = self.resolve_hits() {
+                return reason;
+            }
+
+            if let cpu::State::Locked { .. } = self.cpu.state() {
+                return BreakReason::Locked;
+            }
+        }
+
+        BreakReason::CycleLimit
+    }
+
+    /// Evaluate the conditions of any breakpoints and watchpoints triggered, returning the reason to stop (if any).
+    fn resolve_hits(&mut self) -> Option<BreakReason> {
+        if !self.bus.debugger.has_hits() {
+            return None;
+        }
+
+        // conditions may read memory so the debugger is moved out of the bus while evaluating them
+        let mut debugger = std::mem::take(&mut self.bus.debugger);
+        let reason = debugger.resolve_hits(&self.cpu, &self.bus);
+        self.bus.debugger = debugger;
+        reason
+    }
+
+    /// Like [`GameBoy::update`] but stops early should a breakpoint, watchpoint, or interrupt breakpoint be triggered
+    /// (see [`GameBoy::run_until_break`]).
+    pub fn update_until_break(&mut self, delta: f32) -> BreakReason {
+        let cycles = self.cycles_in(delta);
+        self.run_until_break(cycles)
+    }
+
+    /// The number of cycles the CPU executes in the given number of seconds (twice as many in CGB double speed mode).
+    /// Any fraction of a cycle is carried over to the next call.
+    fn cycles_in(&mut self, delta: f32) -> Cycles {
+        let cycles_per_second = if self.bus.double_speed() {
+            CYCLES_PER_SECOND * 2
+        } else {
+            CYCLES_PER_SECOND
+        };
+
+        let cycles = delta as f64 * cycles_per_second as f64 + self.fractional_cycles;
+        self.fractional_cycles = cycles.fract();
+        cycles as Cycles
     }
 
-    /// Perform a single update 'step'. In other words, fetch and execute a single CPU instruction and based on the
-    /// number of cycles required by that instruction, update the other components of the system.
+    /// Perform a single update 'step'. In other words, fetch and execute a single CPU instruction, updating the other
+    /// components of the system as each M-cycle of that instruction elapses. Returns the number of cycles taken.
     pub fn step(&mut self) -> Cycles {
-        let cycles = self.cpu.cycle(&mut self.bus);
-        self.bus.update(cycles);
-        cycles
+        self.bus.debugger.take_resume_from();
+        self.cpu.cycle(&mut self.bus)
     }
 }
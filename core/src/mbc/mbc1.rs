@@ -37,17 +37,8 @@ impl MBC1 {
 impl super::MemoryBankController for MBC1 {
     fn read8(&self, addr: u16) -> u8 {
         match addr {
-            // ROM bank 0 (or bank 32, 64, or 96 in advanced banking mode)
-            0x0000..=0x3FFF => match self.banking_mode {
-                BankingMode::Simple => self.read_rom_bank(0, addr),
-                BankingMode::Advanced => {
-                    let two_bits = get_bits(self.rom_bank, 5, 7);
-                    self.read_rom_bank(two_bits * 0x20, addr)
-                }
-            },
-
-            // ROM bank 1-127
-            0x4000..=0x7FFF => self.read_rom_bank(self.rom_bank, addr - 0x4000),
+            // ROM bank 0 (or bank 32, 64, or 96 in advanced banking mode) then ROM bank 1-127
+            0x0000..=0x7FFF => self.read_rom_bank(self.rom_bank(addr) as u8, addr & 0x3FFF),
 
             // RAM bank 0-3
             0xA000..=0xBFFF => {
@@ -103,6 +94,28 @@ impl super::MemoryBankController for MBC1 {
             _ => {}
         }
     }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        let bank = match addr {
+            0x0000..=0x3FFF => match self.banking_mode {
+                BankingMode::Simple => 0,
+                BankingMode::Advanced => get_bits(self.rom_bank, 5, 7) * 0x20,
+            },
+            _ => self.rom_bank,
+        };
+        bank as u16
+    }
+
+    fn reset(&mut self) {
+        self.ram_enable = false;
+        self.rom_bank = 1;
+        self.ram_bank = 0;
+        self.banking_mode = BankingMode::Simple;
+    }
+
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        self.ram.as_deref_mut()
+    }
 }
 
 impl MBC1 {
@@ -178,6 +191,21 @@ mod tests {
         assert_eq!(mbc.read8(0x4000), 0xB);
     }
 
+    #[test]
+    fn rom_bank() {
+        let mut mbc = MBC1::new(Cartridge::from_data(vec![0; 0x200000]), false, false);
+        assert_eq!(mbc.rom_bank(0x0000), 0);
+        assert_eq!(mbc.rom_bank(0x4000), 1);
+
+        mbc.write8(0x2000, 3);
+        mbc.write8(0x4000, 1);
+        assert_eq!(mbc.rom_bank(0x3FFF), 0);
+        assert_eq!(mbc.rom_bank(0x7FFF), 35);
+
+        mbc.write8(0x6000, 1); // advanced banking mode
+        assert_eq!(mbc.rom_bank(0x0000), 32);
+    }
+
     #[test]
     fn read_rom_banks_advanced_banking_mode() {
         let mut data = vec![0; 0x200000]; // 2048 KiB
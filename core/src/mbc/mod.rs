@@ -19,4 +19,21 @@ pub fn from_cartridge(cart: Cartridge) -> Option<Box<dyn MemoryBankController>>
 pub trait MemoryBankController {
     fn read8(&self, addr: u16) -> u8;
     fn write8(&mut self, addr: u16, value: u8);
+
+    /// Get the number of the ROM bank currently mapped to the given address (from 0x0000 to 0x7FFF).
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Reset the MBC registers to their power-on state. The contents of cartridge RAM are unaffected.
+    fn reset(&mut self) {}
+
+    /// Get mutable access to the RAM on the cartridge (if there is any).
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
 }
@@ -0,0 +1,268 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::parse::{self, ParseError};
+use crate::screen::Colour;
+
+/// An RGB colour.
+pub type Rgb = [u8; 3];
+
+/// RGB colours for each of the four shades (in the order white, light grey, dark grey, black).
+pub type Shades = [Rgb; 4];
+
+/// The layer a pixel on screen was drawn by, which determines the palette used to display it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layer {
+    /// The background or window.
+    #[default]
+    Background,
+    /// A sprite using object palette 0.
+    Object0,
+    /// A sprite using object palette 1.
+    Object1,
+}
+
+/// Determines the RGB colours with which the shades of each layer are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColourPalette {
+    pub bg: Shades,
+    pub obj0: Shades,
+    pub obj1: Shades,
+}
+
+impl ColourPalette {
+    /// Create a palette in which all layers use the same colours.
+    pub const fn uniform(shades: Shades) -> Self {
+        ColourPalette {
+            bg: shades,
+            obj0: shades,
+            obj1: shades,
+        }
+    }
+
+    /// Get the RGB colour of a shade drawn by the given layer.
+    pub fn rgb(&self, layer: Layer, colour: Colour) -> Rgb {
+        let shades = match layer {
+            Layer::Background => &self.bg,
+            Layer::Object0 => &self.obj0,
+            Layer::Object1 => &self.obj1,
+        };
+        shades[colour as usize]
+    }
+
+    /// Load a palette from a text file (see [`ColourPalette::from_str`] for the format).
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        parse::from_file(path)
+    }
+}
+
+impl Default for ColourPalette {
+    fn default() -> Self {
+        Preset::default().palette()
+    }
+}
+
+/// Parses palettes of the following format, where each line gives the colours of a layer from lightest to darkest as
+/// hexadecimal RGB values. The `obj0` and `obj1` lines are optional and default to the background colours if omitted.
+/// Blank lines and those starting with `#` are ignored.
+///
+/// ```text
+/// # comment
+/// bg   = E0F8D0 88C070 346856 081820
+/// obj0 = FFFFFF FF8484 943A3A 000000
+/// obj1 = FFFFFF 7BFF31 008400 000000
+/// ```
+impl FromStr for ColourPalette {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut bg, mut obj0, mut obj1) = (None, None, None);
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |reason: &str| ParseError {
+                line: index + 1,
+                reason: format!("invalid palette: {reason}"),
+            };
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected '<layer> = <colours>'"))?;
+
+            let slot = match key.trim() {
+                "bg" => &mut bg,
+                "obj0" => &mut obj0,
+                "obj1" => &mut obj1,
+                _ => return Err(error("unknown layer (expected bg, obj0, or obj1)")),
+            };
+
+            *slot = Some(
+                parse_shades(value).ok_or_else(|| error("expected 4 hexadecimal RGB colours"))?,
+            );
+        }
+
+        let bg = bg.ok_or(ParseError {
+            line: 0,
+            reason: "invalid palette: missing bg colours".to_string(),
+        })?;
+
+        Ok(ColourPalette {
+            bg,
+            obj0: obj0.unwrap_or(bg),
+            obj1: obj1.unwrap_or(bg),
+        })
+    }
+}
+
+/// Convert a colour in the RGB555 format used by the CGB and SGB (5 bits each of red, green, and blue from the least
+/// significant bit) to RGB.
+pub(crate) fn rgb555_to_rgb(colour: u16) -> Rgb {
+    [0, 5, 10].map(|shift| {
+        let c = (colour >> shift) & 0x1F;
+        ((c * 255 + 15) / 31) as u8
+    })
+}
+
+fn parse_shades(s: &str) -> Option<Shades> {
+    let colours: Vec<Rgb> = s.split_whitespace().map(parse_rgb).collect::<Option<_>>()?;
+    colours.try_into().ok()
+}
+
+fn parse_rgb(s: &str) -> Option<Rgb> {
+    let s = s.strip_prefix('#').unwrap_or(s);
+    if s.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(s, 16).ok()?;
+    let [_, r, g, b] = value.to_be_bytes();
+    Some([r, g, b])
+}
+
+/// Built-in palettes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preset {
+    /// The green-tinted LCD of the original Game Boy.
+    #[default]
+    DmgGreen,
+    /// The grey LCD of the Game Boy Pocket.
+    PocketGrey,
+    /// The blue-green backlit LCD of the Game Boy Light.
+    Light,
+    /// Pure greyscale from white to black.
+    HighContrast,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::DmgGreen,
+        Preset::PocketGrey,
+        Preset::Light,
+        Preset::HighContrast,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::DmgGreen => "dmg-green",
+            Preset::PocketGrey => "pocket-grey",
+            Preset::Light => "light",
+            Preset::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn palette(&self) -> ColourPalette {
+        ColourPalette::uniform(match self {
+            Preset::DmgGreen => [[155, 188, 15], [139, 172, 15], [48, 98, 48], [15, 56, 15]],
+            Preset::PocketGrey => [[196, 207, 161], [139, 149, 109], [77, 83, 60], [31, 31, 31]],
+            Preset::Light => [[0, 181, 129], [0, 154, 113], [0, 105, 74], [0, 79, 59]],
+            Preset::HighContrast => [[255, 255, 255], [170, 170, 170], [85, 85, 85], [0, 0, 0]],
+        })
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Preset::ALL.iter().map(Preset::name).collect();
+                format!(
+                    "unknown palette '{s}' (expected one of: {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let p: ColourPalette =
+            "# comment\n\nbg = E0F8D0 88C070 346856 081820\nobj1=#FFFFFF #7BFF31 #008400 #000000\n"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            p.bg,
+            [
+                [0xE0, 0xF8, 0xD0],
+                [0x88, 0xC0, 0x70],
+                [0x34, 0x68, 0x56],
+                [0x08, 0x18, 0x20]
+            ]
+        );
+        assert_eq!(p.obj0, p.bg);
+        assert_eq!(p.obj1[1], [0x7B, 0xFF, 0x31]);
+        assert_eq!(p.rgb(Layer::Object1, Colour::DarkGrey), [0x00, 0x84, 0x00]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "obj0 = 000000 000000 000000 000000"
+                .parse::<ColourPalette>()
+                .unwrap_err()
+                .line,
+            0
+        );
+        assert_eq!(
+            "bg = 000000 000000 000000"
+                .parse::<ColourPalette>()
+                .unwrap_err()
+                .line,
+            1
+        );
+        assert_eq!(
+            "\nfoo = 000000 000000 000000 000000"
+                .parse::<ColourPalette>()
+                .unwrap_err()
+                .line,
+            2
+        );
+        assert_eq!("bg 000000".parse::<ColourPalette>().unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn preset_names() {
+        for preset in Preset::ALL {
+            assert_eq!(preset.name().parse(), Ok(preset));
+        }
+        assert!("foo".parse::<Preset>().is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Error produced when parsing one of the line-based text formats accepted by the emulator (symbol files, palettes,
+/// cheat lists).
+#[derive(Debug)]
+pub struct ParseError {
+    /// Line number at which the error occurred (0 if the error does not relate to a specific line).
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "on line {}: {}", self.line, self.reason)
+        } else {
+            write!(f, "{}", self.reason)
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Read and parse a text file, reporting parse errors as [`io::ErrorKind::InvalidData`].
+pub(crate) fn from_file<T: FromStr<Err = ParseError>>(path: impl AsRef<Path>) -> io::Result<T> {
+    std::fs::read_to_string(path)?
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
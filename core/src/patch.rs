@@ -0,0 +1,490 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::cartridge::MAX_ROM_SIZE;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+/// UPS and BPS patches end with the CRC32 checksums of the source, target, and patch (each 4 bytes).
+const FOOTER_SIZE: usize = 12;
+
+/// File extensions of the supported patch formats, in the order they are searched for by [`find_patch`].
+pub const PATCH_EXTENSIONS: [&str; 3] = ["ips", "ups", "bps"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    /// Identify the format of a patch from its header.
+    pub fn detect(patch: &[u8]) -> Option<PatchFormat> {
+        if patch.starts_with(IPS_MAGIC) {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(UPS_MAGIC) {
+            Some(PatchFormat::Ups)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Some(PatchFormat::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+/// Find a patch file beside the given ROM with the same name (e.g., `game.ips` for `game.gb`).
+pub fn find_patch(rom_path: impl AsRef<Path>) -> Option<PathBuf> {
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|ext| rom_path.as_ref().with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+/// Apply an IPS, UPS, or BPS patch to the given ROM data, returning the patched data. The checksums included in UPS
+/// and BPS patches are verified.
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(patch, rom),
+        Some(PatchFormat::Ups) => apply_ups(patch, rom),
+        Some(PatchFormat::Bps) => apply_bps(patch, rom),
+        None => Err(PatchError::UnknownFormat),
+    }
+}
+
+/// IPS patches consist of records each giving a 3-byte offset and 2-byte size (both big endian) followed by the data to
+/// write. A size of 0 indicates a run-length encoded record, given by a 2-byte size and single byte to repeat. The
+/// records are terminated by "EOF", optionally followed by a 3-byte size to truncate the output to.
+fn apply_ips(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = Reader::new(&patch[IPS_MAGIC.len()..]);
+    let mut output = rom.to_vec();
+
+    loop {
+        if reader.remaining().starts_with(IPS_EOF) {
+            reader.take(IPS_EOF.len())?;
+            break;
+        }
+
+        let offset = reader.be(3)?;
+        let (length, data) = match reader.be(2)? {
+            0 => {
+                let length = reader.be(2)?;
+                (length, Bytes::Repeated(reader.byte()?))
+            }
+            length => (length, Bytes::Slice(reader.take(length)?)),
+        };
+
+        let end = offset + length;
+        if end > MAX_ROM_SIZE {
+            return Err(PatchError::Malformed);
+        }
+        if output.len() < end {
+            output.resize(end, 0);
+        }
+        let target = &mut output[offset..end];
+        match data {
+            Bytes::Slice(data) => target.copy_from_slice(data),
+            Bytes::Repeated(value) => target.fill(value),
+        }
+    }
+
+    if reader.remaining().len() >= 3 {
+        output.truncate(reader.be(3)?);
+    }
+
+    Ok(output)
+}
+
+enum Bytes<'a> {
+    Slice(&'a [u8]),
+    Repeated(u8),
+}
+
+/// UPS patches give the source and target sizes followed by hunks each consisting of the number of bytes to skip and
+/// then bytes to XOR with the source until (and including) a 0 byte.
+fn apply_ups(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (body, footer) = split_footer(patch)?;
+    let mut reader = Reader::new(&body[UPS_MAGIC.len()..]);
+
+    let source_size = reader.varint()?;
+    let target_size = checked_target_size(reader.varint()?)?;
+    footer.verify_source(rom, source_size)?;
+
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+
+    let mut offset: usize = 0;
+    while !reader.remaining().is_empty() {
+        offset = offset.saturating_add(reader.varint()?);
+
+        loop {
+            let xor = reader.byte()?;
+            if let Some(byte) = output.get_mut(offset) {
+                *byte ^= xor;
+            }
+            offset = offset.saturating_add(1);
+
+            if xor == 0 {
+                break;
+            }
+        }
+    }
+
+    footer.verify_target(&output)?;
+    Ok(output)
+}
+
+const BPS_SOURCE_READ: usize = 0;
+const BPS_TARGET_READ: usize = 1;
+const BPS_SOURCE_COPY: usize = 2;
+
+/// BPS patches give the source, target, and metadata sizes (followed by the metadata itself) and then a sequence of
+/// actions that each produce some number of bytes of the target by copying from either the source, the patch, or the
+/// target produced so far.
+fn apply_bps(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (body, footer) = split_footer(patch)?;
+    let mut reader = Reader::new(&body[BPS_MAGIC.len()..]);
+
+    let source_size = reader.varint()?;
+    let target_size = checked_target_size(reader.varint()?)?;
+    let metadata_size = reader.varint()?;
+    reader.take(metadata_size)?;
+    footer.verify_source(rom, source_size)?;
+
+    let mut output = Vec::with_capacity(target_size);
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+
+    while !reader.remaining().is_empty() {
+        let action = reader.varint()?;
+        let length = (action >> 2) + 1;
+
+        if length > target_size - output.len() {
+            return Err(PatchError::Malformed);
+        }
+
+        match action & 0b11 {
+            BPS_SOURCE_READ => {
+                let start = output.len();
+                output.extend_from_slice(slice(rom, start, length)?);
+            }
+            BPS_TARGET_READ => output.extend_from_slice(reader.take(length)?),
+            BPS_SOURCE_COPY => {
+                source_offset = reader.relative_offset(source_offset)?;
+                output.extend_from_slice(slice(rom, source_offset, length)?);
+                source_offset += length; // can't overflow as the slice is within the ROM
+            }
+            _ => {
+                // target copy - copied byte by byte as the range copied from may overlap with the bytes being written
+                target_offset = reader.relative_offset(target_offset)?;
+                for _ in 0..length {
+                    let byte = *output.get(target_offset).ok_or(PatchError::Malformed)?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size {
+        return Err(PatchError::Malformed);
+    }
+
+    footer.verify_target(&output)?;
+    Ok(output)
+}
+
+fn slice(data: &[u8], start: usize, length: usize) -> Result<&[u8], PatchError> {
+    let end = start.checked_add(length).ok_or(PatchError::Malformed)?;
+    data.get(start..end).ok_or(PatchError::Malformed)
+}
+
+fn checked_target_size(size: usize) -> Result<usize, PatchError> {
+    if size > MAX_ROM_SIZE {
+        return Err(PatchError::Malformed);
+    }
+    Ok(size)
+}
+
+struct Footer {
+    source_crc: u32,
+    target_crc: u32,
+}
+
+impl Footer {
+    fn verify_source(&self, rom: &[u8], size: usize) -> Result<(), PatchError> {
+        if rom.len() != size || crc32(rom) != self.source_crc {
+            return Err(PatchError::SourceMismatch);
+        }
+        Ok(())
+    }
+
+    fn verify_target(&self, output: &[u8]) -> Result<(), PatchError> {
+        if crc32(output) != self.target_crc {
+            return Err(PatchError::TargetMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Split a UPS or BPS patch into its body and footer, verifying the checksum of the patch itself.
+fn split_footer(patch: &[u8]) -> Result<(&[u8], Footer), PatchError> {
+    if patch.len() < 4 + FOOTER_SIZE {
+        return Err(PatchError::Malformed);
+    }
+
+    let (body, footer) = patch.split_at(patch.len() - FOOTER_SIZE);
+    let crc = |i: usize| u32::from_le_bytes(footer[i * 4..(i + 1) * 4].try_into().unwrap());
+
+    if crc32(&patch[..patch.len() - 4]) != crc(2) {
+        return Err(PatchError::PatchChecksum);
+    }
+
+    Ok((
+        body,
+        Footer {
+            source_crc: crc(0),
+            target_crc: crc(1),
+        },
+    ))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data }
+    }
+
+    fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], PatchError> {
+        if self.data.len() < count {
+            return Err(PatchError::Malformed);
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    /// Read a big endian number of the given number of bytes (as used by IPS).
+    fn be(&mut self, count: usize) -> Result<usize, PatchError> {
+        let bytes = self.take(count)?;
+        Ok(bytes.iter().fold(0, |n, b| (n << 8) | *b as usize))
+    }
+
+    /// Read a variable length number (as used by UPS and BPS) - 7 bits per byte, least significant first, with the
+    /// final byte having its top bit set. Each byte other than the last also adds one to the next 7 bit group so that
+    /// each number has a single encoding.
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+
+        loop {
+            let byte = self.byte()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|n| n.checked_add(value))
+                .ok_or(PatchError::Malformed)?;
+
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+
+            shift = shift.checked_shl(7).ok_or(PatchError::Malformed)?;
+            value = value.checked_add(shift).ok_or(PatchError::Malformed)?;
+        }
+    }
+
+    /// Read a BPS relative offset (sign in bit 0, magnitude in the remaining bits) and apply it to the given offset.
+    fn relative_offset(&mut self, offset: usize) -> Result<usize, PatchError> {
+        let data = self.varint()?;
+        let magnitude = data >> 1;
+
+        if data & 1 != 0 {
+            offset.checked_sub(magnitude)
+        } else {
+            offset.checked_add(magnitude)
+        }
+        .ok_or(PatchError::Malformed)
+    }
+}
+
+/// The CRC32 checksum (as used by zip, gzip, UPS, and BPS).
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchError {
+    UnknownFormat,
+    /// The patch ended unexpectedly, contains an out of range offset, or would produce a ROM larger than 8 MiB.
+    Malformed,
+    /// The checksum of the patch file itself does not match (UPS and BPS only).
+    PatchChecksum,
+    /// The ROM is not the one the patch was created for (UPS and BPS only).
+    SourceMismatch,
+    /// The patched ROM does not have the expected checksum (UPS and BPS only).
+    TargetMismatch,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => {
+                write!(f, "unrecognised patch format (expected IPS, UPS, or BPS)")
+            }
+            PatchError::Malformed => write!(f, "patch is malformed"),
+            PatchError::PatchChecksum => write!(
+                f,
+                "patch checksum does not match (the patch file is corrupt)"
+            ),
+            PatchError::SourceMismatch => {
+                write!(f, "ROM does not match the one the patch was created for")
+            }
+            PatchError::TargetMismatch => write!(f, "patched ROM checksum does not match"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Append the footer of a UPS or BPS patch.
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn ips() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x04, 0xCC]); // RLE extending the ROM
+        patch.extend_from_slice(b"EOF");
+
+        assert_eq!(
+            apply(&patch, &[0; 8]).unwrap(),
+            [0, 0, 0xAA, 0xBB, 0, 0xCC, 0xCC, 0xCC, 0xCC]
+        );
+
+        patch.extend_from_slice(&[0x00, 0x00, 0x03]);
+        assert_eq!(apply(&patch, &[0; 8]).unwrap(), [0, 0, 0xAA]);
+
+        assert_eq!(
+            apply(&patch[..patch.len() - 6], &[0; 8]),
+            Err(PatchError::Malformed)
+        );
+    }
+
+    #[test]
+    fn ups() {
+        let source = [1, 2, 3, 4];
+        let target = [1, 9, 3, 4, 5];
+
+        let mut patch = b"UPS1".to_vec();
+        patch.extend_from_slice(&[0x84, 0x85]); // sizes
+        patch.extend_from_slice(&[0x81, 2 ^ 9, 0x00]); // skip 1, XOR 1 byte
+        patch.extend_from_slice(&[0x81, 5, 0x00]); // skip 1, XOR 1 byte
+        let patch = with_footer(patch, &source, &target);
+
+        assert_eq!(apply(&patch, &source).unwrap(), target);
+        assert_eq!(
+            apply(&patch, &[1, 2, 3, 5]),
+            Err(PatchError::SourceMismatch)
+        );
+
+        let mut corrupt = patch.clone();
+        corrupt[7] ^= 1;
+        assert_eq!(apply(&corrupt, &source), Err(PatchError::PatchChecksum));
+    }
+
+    #[test]
+    fn bps() {
+        let source = [1, 2, 3, 4];
+        let target = [1, 2, 9, 3, 4, 9, 3];
+
+        let mut patch = b"BPS1".to_vec();
+        patch.extend_from_slice(&[0x84, 0x87, 0x80]); // sizes (no metadata)
+        patch.push(0x84); // source read 2
+        patch.extend_from_slice(&[0x81, 9]); // target read 1
+        patch.extend_from_slice(&[0x86, 0x84]); // source copy 2 from offset 2
+        patch.extend_from_slice(&[0x87, 0x84]); // target copy 2 from offset 2
+        let patch = with_footer(patch, &source, &target);
+
+        assert_eq!(apply(&patch, &source).unwrap(), target);
+        assert_eq!(apply(&patch, &[1, 2, 3]), Err(PatchError::SourceMismatch));
+    }
+
+    #[test]
+    fn oversized() {
+        let source = [1, 2, 3, 4];
+
+        // a target size that would overflow when allocated
+        let mut patch = b"BPS1".to_vec();
+        patch.extend_from_slice(&[
+            0x84, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x80, 0x80,
+        ]);
+        let patch = with_footer(patch, &source, &[]);
+        assert_eq!(apply(&patch, &source), Err(PatchError::Malformed));
+
+        let mut patch = b"UPS1".to_vec();
+        patch.extend_from_slice(&[0x84, 0x00, 0x00, 0x00, 0x84]); // over 10 MiB
+        let patch = with_footer(patch, &source, &[]);
+        assert_eq!(apply(&patch, &source), Err(PatchError::Malformed));
+
+        // a source copy from far beyond the end of the ROM
+        let mut patch = b"BPS1".to_vec();
+        patch.extend_from_slice(&[0x84, 0x84, 0x80, 0x8E]);
+        patch.extend_from_slice(&[0x7E, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x80]);
+        let patch = with_footer(patch, &source, &source);
+        assert_eq!(apply(&patch, &source), Err(PatchError::Malformed));
+
+        // actions producing more than the target size
+        let mut patch = b"BPS1".to_vec();
+        patch.extend_from_slice(&[0x84, 0x81, 0x80, 0x84]);
+        let patch = with_footer(patch, &source, &source);
+        assert_eq!(apply(&patch, &source), Err(PatchError::Malformed));
+
+        // an IPS record beyond 8 MiB
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0x00, 0x01, 0xAA]);
+        patch.extend_from_slice(b"EOF");
+        assert_eq!(apply(&patch, &source), Err(PatchError::Malformed));
+    }
+
+    #[test]
+    fn varint() {
+        assert_eq!(Reader::new(&[0x80]).varint(), Ok(0));
+        assert_eq!(Reader::new(&[0xFF]).varint(), Ok(0x7F));
+        assert_eq!(Reader::new(&[0x00, 0x80]).varint(), Ok(0x80));
+        assert_eq!(Reader::new(&[0x01, 0x81]).varint(), Ok(0x101));
+        assert_eq!(Reader::new(&[0x01]).varint(), Err(PatchError::Malformed));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::Model;
+
+/// Seed used for the pseudo-random parts of [`RamInit::ModelTypical`] so that it is deterministic.
+const MODEL_TYPICAL_SEED: u64 = 0x5EED_DEAD_BEEF_CAFE;
+
+/// Policy determining the contents of RAM (work RAM, high RAM, video RAM, and cartridge RAM) when the system is powered
+/// on. Real hardware powers on with semi-random contents which some games rely on (or are broken by), so being able to
+/// choose between policies allows such behaviour to be reproduced deterministically.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RamInit {
+    /// Fill all RAM with 0x00.
+    #[default]
+    Zeros,
+    /// Fill all RAM with 0xFF.
+    Ones,
+    /// Fill all RAM with pseudo-random bytes generated from the given seed.
+    Random { seed: u64 },
+    /// Approximate the contents typically observed on the emulated model. VRAM is cleared (as the boot ROM would do)
+    /// while other RAM holds pseudo-random bytes biased towards 0x00 and 0xFF in alternating blocks - 256-byte blocks of
+    /// work RAM on DMG and 8-byte blocks on CGB.
+    ModelTypical,
+}
+
+/// The region of memory being initialised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamRegion {
+    Wram,
+    Hram,
+    Vram,
+    CartridgeRam,
+}
+
+impl RamInit {
+    /// Fill the given memory region according to this policy.
+    pub fn fill(self, data: &mut [u8], region: RamRegion, model: Model) {
+        match self {
+            RamInit::Zeros => data.fill(0),
+            RamInit::Ones => data.fill(0xFF),
+            RamInit::Random { seed } => {
+                let mut rng = Rng::new(seed, region);
+                data.iter_mut().for_each(|b| *b = rng.next_byte());
+            }
+            RamInit::ModelTypical => fill_model_typical(data, region, model),
+        }
+    }
+}
+
+fn fill_model_typical(data: &mut [u8], region: RamRegion, model: Model) {
+    if region == RamRegion::Vram {
+        data.fill(0);
+        return;
+    }
+
+    let block_size = match (region, model) {
+        (RamRegion::Wram, Model::Dmg | Model::Sgb) => 0x100,
+        _ => 8,
+    };
+
+    let mut rng = Rng::new(MODEL_TYPICAL_SEED, region);
+
+    for (i, b) in data.iter_mut().enumerate() {
+        let (x, y) = (rng.next_byte(), rng.next_byte());
+        *b = if (i / block_size) % 2 == 0 {
+            x & y
+        } else {
+            x | y
+        };
+    }
+}
+
+/// Small deterministic pseudo-random number generator (SplitMix64).
+struct Rng(u64);
+
+impl Rng {
+    /// Create a generator for a given region such that each region gets different contents from the same seed.
+    fn new(seed: u64, region: RamRegion) -> Self {
+        Rng(seed ^ (region as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        (z ^ (z >> 31)) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant() {
+        let mut data = [0x12; 16];
+        RamInit::Ones.fill(&mut data, RamRegion::Wram, Model::Dmg);
+        assert_eq!(data, [0xFF; 16]);
+        RamInit::Zeros.fill(&mut data, RamRegion::Wram, Model::Dmg);
+        assert_eq!(data, [0; 16]);
+    }
+
+    #[test]
+    fn random_is_deterministic() {
+        let init = RamInit::Random { seed: 42 };
+        let (mut a, mut b, mut c) = ([0; 64], [0; 64], [0; 64]);
+        init.fill(&mut a, RamRegion::Wram, Model::Dmg);
+        init.fill(&mut b, RamRegion::Wram, Model::Dmg);
+        init.fill(&mut c, RamRegion::Hram, Model::Dmg);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn model_typical_clears_vram() {
+        let mut data = [0x12; 16];
+        RamInit::ModelTypical.fill(&mut data, RamRegion::Vram, Model::Cgb);
+        assert_eq!(data, [0; 16]);
+    }
+}
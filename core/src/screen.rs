@@ -1,9 +1,13 @@
 use num_derive::FromPrimitive;
 
+use crate::palette::{ColourPalette, Layer, Rgb};
+
 pub const SCREEN_WIDTH: usize = 160;
 pub const SCREEN_HEIGHT: usize = 144;
 
-#[derive(Debug, Clone, Copy, FromPrimitive, PartialEq, PartialOrd)]
+pub(crate) const PIXEL_COUNT: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+
+#[derive(Debug, Clone, Copy, FromPrimitive, PartialEq, Eq, PartialOrd)]
 pub enum Colour {
     White,
     LightGrey,
@@ -11,32 +15,148 @@ pub enum Colour {
     Black,
 }
 
+/// Pixel formats that a completed frame can be written in (see [`Screen::write_frame`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel - red, green, blue, and alpha (always 255).
+    Rgba8888,
+    /// 2 bytes per pixel - 5 bits red, 6 bits green, and 5 bits blue packed into a little-endian 16-bit value.
+    Rgb565,
+    /// 1 byte per pixel - the luminance of the colour.
+    Grayscale8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgba8888 => 4,
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Grayscale8 => 1,
+        }
+    }
+
+    pub(crate) fn write_pixel(&self, pixel: &mut [u8], [r, g, b]: Rgb) {
+        match self {
+            PixelFormat::Rgba8888 => pixel.copy_from_slice(&[r, g, b, 255]),
+            PixelFormat::Rgb565 => {
+                let value = ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3);
+                pixel.copy_from_slice(&value.to_le_bytes());
+            }
+            PixelFormat::Grayscale8 => {
+                pixel[0] = ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8;
+            }
+        }
+    }
+}
+
+/// A pixel on screen - its shade and the layer that drew it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pixel {
+    pub colour: Colour,
+    pub layer: Layer,
+}
+
+/// The screen is double-buffered - the GPU draws to a back buffer which is only made visible (via [`Screen::get`] and
+/// [`Screen::write_frame`]) once the frame is complete so that a half-drawn frame is never displayed.
 pub struct Screen {
-    pixels: [Colour; SCREEN_WIDTH * SCREEN_HEIGHT],
+    /// Frame currently being drawn by the GPU.
+    pixels: [Pixel; PIXEL_COUNT],
+    /// The most recently completed frame.
+    frame: [Pixel; PIXEL_COUNT],
+    /// Number of frames completed so far.
+    frame_count: u64,
+    /// Set when a frame is completed, cleared by [`Screen::take_frame_ready`].
+    frame_ready: bool,
 }
 
 impl Screen {
     pub fn new() -> Self {
         Screen {
-            pixels: [Colour::DarkGrey; SCREEN_WIDTH * SCREEN_HEIGHT],
+            pixels: [Pixel::new(Colour::DarkGrey); PIXEL_COUNT],
+            frame: [Pixel::new(Colour::DarkGrey); PIXEL_COUNT],
+            frame_count: 0,
+            frame_ready: false,
         }
     }
 
-    /// Get the colour of the pixel at the given coordinates. If the given coordinates are out of
-    /// bounds then black is returned.
+    /// Get the colour of the pixel at the given coordinates in the most recently completed frame. If the given
+    /// coordinates are out of bounds then black is returned.
     pub fn get(&self, x: u8, y: u8) -> Colour {
+        self.get_pixel(x, y).colour
+    }
+
+    /// Get the pixel (both colour and layer) at the given coordinates in the most recently completed frame. If the
+    /// given coordinates are out of bounds then a black background pixel is returned.
+    pub fn get_pixel(&self, x: u8, y: u8) -> Pixel {
         if within_bounds(x, y) {
-            self.pixels[index(x, y)]
+            self.frame[index(x, y)]
         } else {
-            Colour::Black
+            Pixel::new(Colour::Black)
         }
     }
 
-    /// Set the colour of the pixel at the given coordinates. If the given coordinates are out of
-    /// bounds then nothing happens.
-    pub fn set(&mut self, x: u8, y: u8, colour: Colour) {
+    /// Get the RGB colour of the pixel at the given coordinates in the most recently completed frame.
+    pub fn get_rgb(&self, x: u8, y: u8, palette: &ColourPalette) -> Rgb {
+        let pixel = self.get_pixel(x, y);
+        palette.rgb(pixel.layer, pixel.colour)
+    }
+
+    /// Set the colour of the pixel at the given coordinates in the frame currently being drawn, drawn by the given
+    /// layer. If the given coordinates are out of bounds then nothing happens.
+    pub fn set(&mut self, x: u8, y: u8, colour: Colour, layer: Layer) {
         if within_bounds(x, y) {
-            self.pixels[index(x, y)] = colour;
+            self.pixels[index(x, y)] = Pixel { colour, layer };
+        }
+    }
+
+    /// Set every pixel to white, as is displayed when the LCD is blanked. Takes effect immediately rather than at the
+    /// end of the current frame.
+    pub fn blank(&mut self) {
+        self.pixels = [Pixel::new(Colour::White); PIXEL_COUNT];
+        self.complete_frame();
+    }
+
+    /// Make the frame currently being drawn visible. Called by the GPU upon entering VBlank.
+    pub fn complete_frame(&mut self) {
+        self.frame = self.pixels;
+        self.frame_count += 1;
+        self.frame_ready = true;
+    }
+
+    /// Number of frames completed since the system was powered on.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Returns whether a new frame has been completed since this method was last called.
+    pub fn take_frame_ready(&mut self) -> bool {
+        std::mem::take(&mut self.frame_ready)
+    }
+
+    /// Write the most recently completed frame to the given buffer in the specified pixel format (row by row from the
+    /// top-left), using the given palette to determine the colour of each pixel. The buffer must be exactly large
+    /// enough to hold a frame in that format.
+    pub fn write_frame(&self, buffer: &mut [u8], format: PixelFormat, palette: &ColourPalette) {
+        assert_eq!(
+            buffer.len(),
+            PIXEL_COUNT * format.bytes_per_pixel(),
+            "buffer size does not match frame size in {format:?} format"
+        );
+
+        for (bytes, pixel) in buffer
+            .chunks_exact_mut(format.bytes_per_pixel())
+            .zip(self.frame.iter())
+        {
+            format.write_pixel(bytes, palette.rgb(pixel.layer, pixel.colour));
+        }
+    }
+}
+
+impl Pixel {
+    const fn new(colour: Colour) -> Self {
+        Pixel {
+            colour,
+            layer: Layer::Background,
         }
     }
 }
@@ -56,3 +176,53 @@ fn within_bounds(x: u8, y: u8) -> bool {
 fn index(x: u8, y: u8) -> usize {
     y as usize * SCREEN_WIDTH + x as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE: ColourPalette = ColourPalette {
+        bg: [[255, 255, 255], [170, 170, 170], [85, 85, 85], [0, 0, 0]],
+        obj0: [[255, 0, 0]; 4],
+        obj1: [[0, 0, 255]; 4],
+    };
+
+    #[test]
+    fn double_buffering() {
+        let mut s = Screen::new();
+        s.set(1, 2, Colour::Black, Layer::Background);
+        assert_eq!(s.get(1, 2), Colour::DarkGrey);
+        assert!(!s.take_frame_ready());
+
+        s.complete_frame();
+        assert_eq!(s.get(1, 2), Colour::Black);
+        assert_eq!(s.frame_count(), 1);
+        assert!(s.take_frame_ready());
+        assert!(!s.take_frame_ready());
+    }
+
+    #[test]
+    fn write_frame() {
+        let mut s = Screen::new();
+        s.set(0, 0, Colour::White, Layer::Background);
+        s.set(1, 0, Colour::Black, Layer::Background);
+        s.set(3, 0, Colour::Black, Layer::Object1);
+        s.complete_frame();
+
+        let mut rgba = vec![0; PIXEL_COUNT * 4];
+        s.write_frame(&mut rgba, PixelFormat::Rgba8888, &PALETTE);
+        assert_eq!(
+            rgba[..12],
+            [255, 255, 255, 255, 0, 0, 0, 255, 85, 85, 85, 255]
+        );
+        assert_eq!(rgba[12..16], [0, 0, 255, 255]);
+
+        let mut rgb565 = vec![0; PIXEL_COUNT * 2];
+        s.write_frame(&mut rgb565, PixelFormat::Rgb565, &PALETTE);
+        assert_eq!(rgb565[..4], [0xFF, 0xFF, 0x00, 0x00]);
+
+        let mut grey = vec![0; PIXEL_COUNT];
+        s.write_frame(&mut grey, PixelFormat::Grayscale8, &PALETTE);
+        assert_eq!(grey[..3], [255, 0, 85]);
+    }
+}
@@ -15,8 +15,8 @@ impl SerialTransfer {
 
     pub fn update(&mut self) {
         // transfer requested, use internal clock
-        if self.control == 0x81 {
-            self.control = 0x01; // no transfer in progress/requested
+        if self.control & 0x81 == 0x81 {
+            self.control &= 0x7F; // no transfer in progress/requested
             self.byte = Some(self.data);
         }
     }
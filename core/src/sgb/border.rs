@@ -0,0 +1,112 @@
+use super::TRANSFER_SIZE;
+
+/// Border tiles are 8x8 pixels with 4 bits per pixel in the SNES planar format.
+const TILE_SIZE_BYTES: usize = 32;
+const TILE_COUNT: usize = 256;
+const TILE_WIDTH: usize = 8;
+
+/// The border map covers the whole 256x224 SGB output.
+const MAP_WIDTH: usize = 32;
+const MAP_HEIGHT: usize = 28;
+/// Offset of the border palettes within the data sent by PCT_TRN (following the 32x32 entry map).
+const PALETTES_OFFSET: usize = 0x800;
+const PALETTE_COUNT: usize = 4;
+const PALETTE_SIZE: usize = 16;
+
+/// The border displayed around the Game Boy screen, set by the CHR_TRN and PCT_TRN commands.
+pub struct Border {
+    tiles: Box<[u8; TILE_COUNT * TILE_SIZE_BYTES]>,
+    /// Map entries - the tile index (bits 0-7), palette (bits 10-12), and whether to flip the tile horizontally (bit
+    /// 14) and vertically (bit 15).
+    map: [u16; MAP_WIDTH * MAP_HEIGHT],
+    /// Border palettes (SGB palettes 4 to 7) in RGB555 format.
+    palettes: [[u16; PALETTE_SIZE]; PALETTE_COUNT],
+}
+
+impl Border {
+    pub fn new() -> Self {
+        Border {
+            tiles: Box::new([0; TILE_COUNT * TILE_SIZE_BYTES]),
+            map: [0; MAP_WIDTH * MAP_HEIGHT],
+            palettes: [[0; PALETTE_SIZE]; PALETTE_COUNT],
+        }
+    }
+
+    /// Set either the lower (0x00 to 0x7F) or upper (0x80 to 0xFF) half of the border tiles from data sent by CHR_TRN.
+    pub fn set_tiles(&mut self, upper: bool, data: &[u8]) {
+        let start = if upper { TRANSFER_SIZE } else { 0 };
+        self.tiles[start..start + TRANSFER_SIZE].copy_from_slice(&data[..TRANSFER_SIZE]);
+    }
+
+    /// Set the border map and palettes from data sent by PCT_TRN.
+    pub fn set_map_and_palettes(&mut self, data: &[u8]) {
+        for (entry, bytes) in self.map.iter_mut().zip(data.chunks_exact(2)) {
+            *entry = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+
+        let colours = data[PALETTES_OFFSET..].chunks_exact(2);
+        for (colour, bytes) in self.palettes.iter_mut().flatten().zip(colours) {
+            *colour = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+    }
+
+    /// Get the RGB555 colour of the border at the given coordinates of the SGB output, or `None` if the border is
+    /// transparent there (colour 0 of each border palette is transparent).
+    pub fn colour(&self, x: usize, y: usize) -> Option<u16> {
+        let entry = self.map[(y / TILE_WIDTH) * MAP_WIDTH + x / TILE_WIDTH];
+
+        let tile = (entry & 0xFF) as usize;
+        let palette = ((entry >> 10) & 0b11) as usize;
+
+        let mut tile_x = x % TILE_WIDTH;
+        let mut tile_y = y % TILE_WIDTH;
+        if entry & 0x4000 != 0 {
+            tile_x = TILE_WIDTH - 1 - tile_x;
+        }
+        if entry & 0x8000 != 0 {
+            tile_y = TILE_WIDTH - 1 - tile_y;
+        }
+
+        // bit planes 0 and 1 are interleaved in the first 16 bytes of the tile, planes 2 and 3 in the last 16
+        let data = &self.tiles[tile * TILE_SIZE_BYTES..(tile + 1) * TILE_SIZE_BYTES];
+        let index = (0..4).fold(0, |index, plane| {
+            let byte = data[(plane / 2) * 16 + tile_y * 2 + plane % 2];
+            index | (((byte >> (7 - tile_x)) & 1) as usize) << plane
+        });
+
+        (index != 0).then(|| self.palettes[palette][index])
+    }
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Border::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn border_colour() {
+        let mut b = Border::new();
+        assert_eq!(b.colour(0, 0), None);
+
+        // tile 0x81 has colour index 0b1001 in its top-left pixel
+        let mut tiles = vec![0; TRANSFER_SIZE];
+        tiles[TILE_SIZE_BYTES] = 0x80;
+        tiles[TILE_SIZE_BYTES + 16 + 1] = 0x80;
+        b.set_tiles(true, &tiles);
+
+        // place it at map position (1, 0) using palette 5 and flipped horizontally
+        let mut pct = vec![0; TRANSFER_SIZE];
+        pct[2..4].copy_from_slice(&(0x81 | 5 << 10 | 0x4000u16).to_le_bytes());
+        pct[PALETTES_OFFSET + PALETTE_SIZE * 2 + 9 * 2] = 0x1F;
+        b.set_map_and_palettes(&pct);
+
+        assert_eq!(b.colour(15, 0), Some(0x001F));
+        assert_eq!(b.colour(8, 0), None);
+        assert_eq!(b.colour(15, 1), None);
+    }
+}
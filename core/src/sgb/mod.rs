@@ -0,0 +1,598 @@
+mod border;
+mod packet;
+
+use border::Border;
+#[cfg(test)]
+pub use packet::send_packet;
+pub use packet::{Packet, PacketReceiver, PACKET_SIZE};
+
+use crate::palette::{rgb555_to_rgb, Rgb};
+use crate::screen::{PixelFormat, Screen, PIXEL_COUNT, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Width of the SGB output (the Game Boy screen surrounded by the border).
+pub const SGB_SCREEN_WIDTH: usize = 256;
+/// Height of the SGB output (the Game Boy screen surrounded by the border).
+pub const SGB_SCREEN_HEIGHT: usize = 224;
+
+/// Position of the top-left of the Game Boy screen within the SGB output.
+const SCREEN_X: usize = (SGB_SCREEN_WIDTH - SCREEN_WIDTH) / 2;
+const SCREEN_Y: usize = (SGB_SCREEN_HEIGHT - SCREEN_HEIGHT) / 2;
+
+/// Palettes are assigned to the Game Boy screen in blocks of 8x8 pixels.
+const ATTRIBUTE_BLOCK_SIZE: usize = 8;
+const ATTRIBUTE_WIDTH: usize = SCREEN_WIDTH / ATTRIBUTE_BLOCK_SIZE;
+const ATTRIBUTE_HEIGHT: usize = SCREEN_HEIGHT / ATTRIBUTE_BLOCK_SIZE;
+const ATTRIBUTE_COUNT: usize = ATTRIBUTE_WIDTH * ATTRIBUTE_HEIGHT;
+
+/// Number of bytes transferred by the VRAM transfer commands (PAL_TRN, CHR_TRN, PCT_TRN, and ATTR_TRN).
+const TRANSFER_SIZE: usize = 0x1000;
+const TRANSFER_TILE_SIZE_BYTES: usize = 16;
+
+const SYSTEM_PALETTE_COUNT: usize = 512;
+const ATTRIBUTE_FILE_COUNT: usize = 45;
+/// Attribute files hold 2 bits per block.
+const ATTRIBUTE_FILE_SIZE: usize = ATTRIBUTE_COUNT / 4;
+
+/// Colours of the four palettes prior to being set by the game.
+const DEFAULT_PALETTE: [u16; 4] = [0x67BF, 0x265B, 0x10B5, 0x2866];
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0A;
+const PAL_TRN: u8 = 0x0B;
+const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+const MASK_EN: u8 = 0x17;
+
+/// How the Game Boy screen is masked, set by MASK_EN. Games typically mask the screen while performing VRAM transfers
+/// so that the transferred data isn't displayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mask {
+    #[default]
+    None,
+    /// Keep displaying the screen as it was when the mask was set.
+    Freeze,
+    Black,
+    /// Display the screen entirely in colour 0.
+    Colour0,
+}
+
+/// Data that a game can send by displaying it on screen following the relevant command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    SystemPalettes,
+    BorderTiles { upper: bool },
+    BorderMap,
+    AttributeFiles,
+}
+
+/// The Super Game Boy. Games send commands as packets through the P1 register (see [`PacketReceiver`]) to colourise
+/// regions of the screen and display a border around it.
+pub struct Sgb {
+    /// Bytes of the command currently being received (commands can span up to 7 packets).
+    command: Vec<u8>,
+    /// The four palettes used to colourise the screen in RGB555 format. Colour 0 is shared by all palettes.
+    palettes: [[u16; 4]; 4],
+    /// Palettes that may be selected with PAL_SET, sent by PAL_TRN.
+    system_palettes: Box<[[u16; 4]; SYSTEM_PALETTE_COUNT]>,
+    /// The palette used by each 8x8 block of the screen.
+    attributes: [u8; ATTRIBUTE_COUNT],
+    /// Attribute maps that may be selected with ATTR_SET, sent by ATTR_TRN.
+    attribute_files: Box<[[u8; ATTRIBUTE_FILE_SIZE]; ATTRIBUTE_FILE_COUNT]>,
+    mask: Mask,
+    /// Colours of the screen at the point it was frozen by MASK_EN.
+    frozen: Box<[u16; PIXEL_COUNT]>,
+    border: Border,
+    /// A transfer requested by the game along with the frame count at the time of the request. The transfer takes
+    /// place once the next frame has been completed.
+    pending_transfer: Option<(Transfer, u64)>,
+    /// Number of players requested by MLT_REQ.
+    players: usize,
+}
+
+impl Sgb {
+    pub fn new() -> Self {
+        Sgb {
+            command: Vec::new(),
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: Box::new([[0; 4]; SYSTEM_PALETTE_COUNT]),
+            attributes: [0; ATTRIBUTE_COUNT],
+            attribute_files: Box::new([[0; ATTRIBUTE_FILE_SIZE]; ATTRIBUTE_FILE_COUNT]),
+            mask: Mask::None,
+            frozen: Box::new([0; PIXEL_COUNT]),
+            border: Border::new(),
+            pending_transfer: None,
+            players: 1,
+        }
+    }
+
+    pub fn mask(&self) -> Mask {
+        self.mask
+    }
+
+    /// Number of players requested by the game (1, 2, or 4).
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    /// Handle a packet sent by the game. Commands are executed once all of their packets have been received.
+    pub fn receive_packet(&mut self, packet: Packet, screen: &Screen) {
+        if self.command.is_empty() && packet[0] & 0b111 == 0 {
+            log::warn!("ignoring SGB packet with a length of 0");
+            return;
+        }
+
+        self.command.extend_from_slice(&packet);
+
+        let length = (self.command[0] & 0b111) as usize;
+        if self.command.len() >= length * PACKET_SIZE {
+            let command = std::mem::take(&mut self.command);
+            self.execute(&command, screen);
+        }
+    }
+
+    /// Perform any pending VRAM transfer should a frame have been completed since it was requested.
+    pub fn update(&mut self, screen: &Screen) {
+        match self.pending_transfer {
+            Some((transfer, frame)) if screen.frame_count() > frame => {
+                self.pending_transfer = None;
+                self.transfer(transfer, &transfer_data(screen));
+            }
+            _ => {}
+        }
+    }
+
+    fn execute(&mut self, data: &[u8], screen: &Screen) {
+        let command = data[0] >> 3;
+        log::debug!("executing SGB command {command:#04X}");
+
+        match command {
+            PAL01 => self.set_palette_pair(0, 1, data),
+            PAL23 => self.set_palette_pair(2, 3, data),
+            PAL03 => self.set_palette_pair(0, 3, data),
+            PAL12 => self.set_palette_pair(1, 2, data),
+            ATTR_BLK => self.attribute_blocks(data),
+            ATTR_LIN => self.attribute_lines(data),
+            ATTR_DIV => self.attribute_divide(data),
+            ATTR_CHR => self.attribute_characters(data),
+            PAL_SET => self.set_system_palettes(data, screen),
+            PAL_TRN => self.request_transfer(Transfer::SystemPalettes, screen),
+            MLT_REQ => {
+                self.players = match data[1] & 0b11 {
+                    1 => 2,
+                    3 => 4,
+                    _ => 1,
+                }
+            }
+            CHR_TRN => self.request_transfer(
+                Transfer::BorderTiles {
+                    upper: data[1] & 1 != 0,
+                },
+                screen,
+            ),
+            PCT_TRN => self.request_transfer(Transfer::BorderMap, screen),
+            ATTR_TRN => self.request_transfer(Transfer::AttributeFiles, screen),
+            ATTR_SET => {
+                self.apply_attribute_file(data[1] & 0x3F);
+                if data[1] & 0x40 != 0 {
+                    self.set_mask(Mask::None, screen);
+                }
+            }
+            MASK_EN => {
+                let mask = match data[1] & 0b11 {
+                    0 => Mask::None,
+                    1 => Mask::Freeze,
+                    2 => Mask::Black,
+                    _ => Mask::Colour0,
+                };
+                self.set_mask(mask, screen);
+            }
+            _ => log::debug!("ignoring unsupported SGB command {command:#04X}"),
+        }
+    }
+
+    /// PAL01, PAL23, PAL03, and PAL12 - set colour 0 (shared by all palettes) and colours 1 to 3 of two palettes.
+    fn set_palette_pair(&mut self, first: usize, second: usize, data: &[u8]) {
+        let colours: Vec<u16> = data[1..15]
+            .chunks_exact(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+
+        self.palettes[first][1..].copy_from_slice(&colours[1..4]);
+        self.palettes[second][1..].copy_from_slice(&colours[4..7]);
+        self.set_shared_colour(colours[0]);
+    }
+
+    fn set_shared_colour(&mut self, colour: u16) {
+        for palette in &mut self.palettes {
+            palette[0] = colour;
+        }
+    }
+
+    /// PAL_SET - set all four palettes to system palettes and optionally apply an attribute file.
+    fn set_system_palettes(&mut self, data: &[u8], screen: &Screen) {
+        for (palette, bytes) in self.palettes.iter_mut().zip(data[1..9].chunks_exact(2)) {
+            let index = u16::from_le_bytes([bytes[0], bytes[1]]) as usize % SYSTEM_PALETTE_COUNT;
+            *palette = self.system_palettes[index];
+        }
+        self.set_shared_colour(self.palettes[0][0]);
+
+        let attributes = data[9];
+        if attributes & 0x80 != 0 {
+            self.apply_attribute_file(attributes & 0x3F);
+        }
+        if attributes & 0x40 != 0 {
+            self.set_mask(Mask::None, screen);
+        }
+    }
+
+    /// ATTR_BLK - set the palettes inside, outside, and on the border of rectangular areas of the screen.
+    fn attribute_blocks(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+
+        for set in data[2..].chunks_exact(6).take(count) {
+            let control = set[0] & 0b111;
+            let [inside, border, outside] = [0, 2, 4].map(|shift| (set[1] >> shift) & 0b11);
+            let [left, top, right, bottom] = [set[2], set[3], set[4], set[5]].map(usize::from);
+
+            // when only one of the inside and outside are changed, the border is given the same palette
+            let border = match control {
+                0b001 => Some(inside),
+                0b100 => Some(outside),
+                _ => (control & 0b010 != 0).then_some(border),
+            };
+            let inside = (control & 0b001 != 0).then_some(inside);
+            let outside = (control & 0b100 != 0).then_some(outside);
+
+            self.set_attributes(|x, y| {
+                let within = (left..=right).contains(&x) && (top..=bottom).contains(&y);
+                if !within {
+                    outside
+                } else if x == left || x == right || y == top || y == bottom {
+                    border
+                } else {
+                    inside
+                }
+            });
+        }
+    }
+
+    /// ATTR_LIN - set the palette of individual rows or columns of the screen.
+    fn attribute_lines(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+
+        for &line in data[2..].iter().take(count) {
+            let index = (line & 0x1F) as usize;
+            let palette = (line >> 5) & 0b11;
+            let horizontal = line & 0x80 != 0;
+
+            self.set_attributes(|x, y| {
+                let on_line = if horizontal { y == index } else { x == index };
+                on_line.then_some(palette)
+            });
+        }
+    }
+
+    /// ATTR_DIV - divide the screen in two with a line, setting the palettes either side of and on that line.
+    fn attribute_divide(&mut self, data: &[u8]) {
+        let [after, before, on_line] = [0, 2, 4].map(|shift| (data[1] >> shift) & 0b11);
+        let horizontal = data[1] & 0x40 != 0;
+        let position = data[2] as usize;
+
+        self.set_attributes(|x, y| {
+            let coordinate = if horizontal { y } else { x };
+            Some(match coordinate.cmp(&position) {
+                std::cmp::Ordering::Less => before,
+                std::cmp::Ordering::Equal => on_line,
+                std::cmp::Ordering::Greater => after,
+            })
+        });
+    }
+
+    /// ATTR_CHR - set the palettes of a sequence of blocks starting from a given position, either row by row or
+    /// column by column.
+    fn attribute_characters(&mut self, data: &[u8]) {
+        let (mut x, mut y) = (data[1] as usize, data[2] as usize);
+        let count = u16::from_le_bytes([data[3], data[4]]) as usize;
+        let vertical = data[5] & 1 != 0;
+
+        for index in 0..count.min(ATTRIBUTE_COUNT) {
+            let Some(byte) = data.get(6 + index / 4) else {
+                break;
+            };
+            if x < ATTRIBUTE_WIDTH && y < ATTRIBUTE_HEIGHT {
+                self.attributes[y * ATTRIBUTE_WIDTH + x] = (byte >> (6 - (index % 4) * 2)) & 0b11;
+            }
+
+            if vertical {
+                y += 1;
+                if y >= ATTRIBUTE_HEIGHT {
+                    y = 0;
+                    x += 1;
+                }
+            } else {
+                x += 1;
+                if x >= ATTRIBUTE_WIDTH {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+
+    /// Set the palette of each block of the screen for which the given function returns a palette.
+    fn set_attributes(&mut self, f: impl Fn(usize, usize) -> Option<u8>) {
+        for (index, attribute) in self.attributes.iter_mut().enumerate() {
+            if let Some(palette) = f(index % ATTRIBUTE_WIDTH, index / ATTRIBUTE_WIDTH) {
+                *attribute = palette;
+            }
+        }
+    }
+
+    fn apply_attribute_file(&mut self, file: u8) {
+        let Some(file) = self.attribute_files.get(file as usize) else {
+            log::warn!("SGB attribute file {file} does not exist");
+            return;
+        };
+
+        for (index, attribute) in self.attributes.iter_mut().enumerate() {
+            *attribute = (file[index / 4] >> (6 - (index % 4) * 2)) & 0b11;
+        }
+    }
+
+    fn set_mask(&mut self, mask: Mask, screen: &Screen) {
+        if mask == Mask::Freeze && self.mask != Mask::Freeze {
+            let mut frozen = Box::new([0; PIXEL_COUNT]);
+            for (index, colour) in frozen.iter_mut().enumerate() {
+                *colour = self.screen_colour(screen, index % SCREEN_WIDTH, index / SCREEN_WIDTH);
+            }
+            self.frozen = frozen;
+        }
+        self.mask = mask;
+    }
+
+    fn request_transfer(&mut self, transfer: Transfer, screen: &Screen) {
+        self.pending_transfer = Some((transfer, screen.frame_count()));
+    }
+
+    fn transfer(&mut self, transfer: Transfer, data: &[u8]) {
+        log::debug!("performing SGB transfer {transfer:?}");
+
+        match transfer {
+            Transfer::SystemPalettes => {
+                for (colour, bytes) in self
+                    .system_palettes
+                    .iter_mut()
+                    .flatten()
+                    .zip(data.chunks_exact(2))
+                {
+                    *colour = u16::from_le_bytes([bytes[0], bytes[1]]);
+                }
+            }
+            Transfer::BorderTiles { upper } => self.border.set_tiles(upper, data),
+            Transfer::BorderMap => self.border.set_map_and_palettes(data),
+            Transfer::AttributeFiles => {
+                for (file, bytes) in self
+                    .attribute_files
+                    .iter_mut()
+                    .zip(data.chunks_exact(ATTRIBUTE_FILE_SIZE))
+                {
+                    file.copy_from_slice(bytes);
+                }
+            }
+        }
+    }
+
+    /// Get the RGB555 colour of a pixel of the Game Boy screen as colourised by the current palettes, ignoring the
+    /// mask.
+    fn screen_colour(&self, screen: &Screen, x: usize, y: usize) -> u16 {
+        let block = (y / ATTRIBUTE_BLOCK_SIZE) * ATTRIBUTE_WIDTH + x / ATTRIBUTE_BLOCK_SIZE;
+        let palette = self.attributes[block] as usize;
+        self.palettes[palette][screen.get(x as u8, y as u8) as usize]
+    }
+
+    /// Get the RGB colour of the pixel at the given coordinates of the SGB output - the Game Boy screen (colourised
+    /// and masked) in the centre, surrounded by the border. Where the border is transparent, colour 0 is displayed.
+    pub fn get_rgb(&self, screen: &Screen, x: usize, y: usize) -> Rgb {
+        let (screen_x, screen_y) = (x.wrapping_sub(SCREEN_X), y.wrapping_sub(SCREEN_Y));
+
+        let colour = if screen_x < SCREEN_WIDTH && screen_y < SCREEN_HEIGHT {
+            match self.mask {
+                Mask::None => self.screen_colour(screen, screen_x, screen_y),
+                Mask::Freeze => self.frozen[screen_y * SCREEN_WIDTH + screen_x],
+                Mask::Black => 0,
+                Mask::Colour0 => self.palettes[0][0],
+            }
+        } else {
+            self.border.colour(x, y).unwrap_or(self.palettes[0][0])
+        };
+
+        rgb555_to_rgb(colour)
+    }
+
+    /// Write the SGB output (see [`Sgb::get_rgb`]) for the most recently completed frame of the given screen to the
+    /// given buffer in the specified pixel format. The buffer must be exactly large enough to hold the 256x224 output
+    /// in that format.
+    pub fn write_frame(&self, screen: &Screen, buffer: &mut [u8], format: PixelFormat) {
+        assert_eq!(
+            buffer.len(),
+            SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT * format.bytes_per_pixel(),
+            "buffer size does not match SGB output size in {format:?} format"
+        );
+
+        for (index, bytes) in buffer
+            .chunks_exact_mut(format.bytes_per_pixel())
+            .enumerate()
+        {
+            let rgb = self.get_rgb(screen, index % SGB_SCREEN_WIDTH, index / SGB_SCREEN_WIDTH);
+            format.write_pixel(bytes, rgb);
+        }
+    }
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Sgb::new()
+    }
+}
+
+/// Read the data of a VRAM transfer from the most recently completed frame. The SGB reads the screen as a sequence of
+/// 8x8 tiles (row by row from the top-left) in the Game Boy's 2 bits per pixel tile format.
+fn transfer_data(screen: &Screen) -> Vec<u8> {
+    let mut data = vec![0; TRANSFER_SIZE];
+
+    for (tile, bytes) in data.chunks_exact_mut(TRANSFER_TILE_SIZE_BYTES).enumerate() {
+        let tile_x = (tile % ATTRIBUTE_WIDTH) * ATTRIBUTE_BLOCK_SIZE;
+        let tile_y = (tile / ATTRIBUTE_WIDTH) * ATTRIBUTE_BLOCK_SIZE;
+
+        for (line, pair) in bytes.chunks_exact_mut(2).enumerate() {
+            for pixel in 0..ATTRIBUTE_BLOCK_SIZE {
+                let colour = screen.get((tile_x + pixel) as u8, (tile_y + line) as u8) as u8;
+                pair[0] |= (colour & 1) << (7 - pixel);
+                pair[1] |= (colour >> 1) << (7 - pixel);
+            }
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::Layer;
+    use crate::screen::Colour;
+
+    fn send(sgb: &mut Sgb, screen: &Screen, command: u8, data: &[u8]) {
+        let length = (data.len() + 1).div_ceil(PACKET_SIZE).max(1);
+        let mut bytes = vec![(command << 3) | length as u8];
+        bytes.extend_from_slice(data);
+        bytes.resize(length * PACKET_SIZE, 0);
+
+        for packet in bytes.chunks_exact(PACKET_SIZE) {
+            sgb.receive_packet(packet.try_into().unwrap(), screen);
+        }
+    }
+
+    fn palette_at(sgb: &Sgb, x: usize, y: usize) -> u8 {
+        sgb.attributes[y * ATTRIBUTE_WIDTH + x]
+    }
+
+    #[test]
+    fn set_palettes() {
+        let (mut sgb, screen) = (Sgb::new(), Screen::new());
+        let colours: Vec<u8> = (1..=7u16).flat_map(u16::to_le_bytes).collect();
+        send(&mut sgb, &screen, PAL12, &colours);
+
+        assert_eq!(sgb.palettes[1], [1, 2, 3, 4]);
+        assert_eq!(sgb.palettes[2], [1, 5, 6, 7]);
+        assert_eq!(sgb.palettes[0], [1, 0x265B, 0x10B5, 0x2866]);
+    }
+
+    #[test]
+    fn attribute_block() {
+        let (mut sgb, screen) = (Sgb::new(), Screen::new());
+        // inside only (border takes the inside palette), palette 1, from (2, 3) to (5, 6)
+        send(&mut sgb, &screen, ATTR_BLK, &[1, 0b001, 0b01, 2, 3, 5, 6]);
+
+        assert_eq!(palette_at(&sgb, 2, 3), 1);
+        assert_eq!(palette_at(&sgb, 4, 4), 1);
+        assert_eq!(palette_at(&sgb, 6, 6), 0);
+
+        // border palette 2 and outside palette 3
+        send(
+            &mut sgb,
+            &screen,
+            ATTR_BLK,
+            &[1, 0b110, 0b11_10_00, 2, 3, 5, 6],
+        );
+        assert_eq!(palette_at(&sgb, 2, 3), 2);
+        assert_eq!(palette_at(&sgb, 4, 4), 1);
+        assert_eq!(palette_at(&sgb, 0, 0), 3);
+    }
+
+    #[test]
+    fn attribute_line_and_divide() {
+        let (mut sgb, screen) = (Sgb::new(), Screen::new());
+        send(&mut sgb, &screen, ATTR_DIV, &[0b00_01_10_11, 10]);
+        assert_eq!(palette_at(&sgb, 9, 0), 2);
+        assert_eq!(palette_at(&sgb, 10, 5), 1);
+        assert_eq!(palette_at(&sgb, 11, 17), 3);
+
+        // horizontal line 4 with palette 3
+        send(&mut sgb, &screen, ATTR_LIN, &[1, 0x80 | 3 << 5 | 4]);
+        assert_eq!(palette_at(&sgb, 0, 4), 3);
+        assert_eq!(palette_at(&sgb, 0, 3), 2);
+    }
+
+    #[test]
+    fn attribute_characters() {
+        let (mut sgb, screen) = (Sgb::new(), Screen::new());
+        send(
+            &mut sgb,
+            &screen,
+            ATTR_CHR,
+            &[19, 0, 3, 0, 0, 0b01_10_11_00],
+        );
+        assert_eq!(palette_at(&sgb, 19, 0), 1);
+        assert_eq!(palette_at(&sgb, 0, 1), 2);
+        assert_eq!(palette_at(&sgb, 1, 1), 3);
+        assert_eq!(palette_at(&sgb, 2, 1), 0);
+    }
+
+    #[test]
+    fn transfer_palettes() {
+        let (mut sgb, mut screen) = (Sgb::new(), Screen::new());
+        send(&mut sgb, &screen, PAL_TRN, &[]);
+
+        // the first pixel of the first tile is colour 3, so the first two bytes of the transfer are 0x80
+        for x in 0..SCREEN_WIDTH as u8 {
+            for y in 0..SCREEN_HEIGHT as u8 {
+                screen.set(x, y, Colour::White, Layer::Background);
+            }
+        }
+        screen.set(0, 0, Colour::Black, Layer::Background);
+
+        sgb.update(&screen);
+        assert_eq!(sgb.system_palettes[0][0], 0);
+
+        screen.complete_frame();
+        sgb.update(&screen);
+        assert_eq!(sgb.system_palettes[0][0], 0x8080);
+
+        send(&mut sgb, &screen, PAL_SET, &[0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(sgb.palettes[3], [0x8080, 0, 0, 0]);
+    }
+
+    #[test]
+    fn output() {
+        let (mut sgb, mut screen) = (Sgb::new(), Screen::new());
+        screen.set(0, 0, Colour::Black, Layer::Background);
+        screen.complete_frame();
+
+        let colours: Vec<u8> = [0x7FFF, 0, 0, 0x001F, 0, 0, 0]
+            .into_iter()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        send(&mut sgb, &screen, PAL01, &colours);
+
+        assert_eq!(sgb.get_rgb(&screen, SCREEN_X, SCREEN_Y), [255, 0, 0]);
+        assert_eq!(sgb.get_rgb(&screen, 0, 0), [255, 255, 255]);
+
+        send(&mut sgb, &screen, MASK_EN, &[2]);
+        assert_eq!(sgb.get_rgb(&screen, SCREEN_X, SCREEN_Y), [0, 0, 0]);
+
+        let mut buffer = vec![0; SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT * 4];
+        sgb.write_frame(&screen, &mut buffer, PixelFormat::Rgba8888);
+        assert_eq!(buffer[..4], [255, 255, 255, 255]);
+    }
+}
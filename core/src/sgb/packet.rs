@@ -0,0 +1,125 @@
+/// Number of bytes in a single SGB packet.
+pub const PACKET_SIZE: usize = 16;
+
+pub type Packet = [u8; PACKET_SIZE];
+
+const PACKET_BITS: usize = PACKET_SIZE * 8;
+
+/// Select bits of P1 with both P14 and P15 low, which resets the receiver to begin a new packet.
+const RESET_PULSE: u8 = 0b00_0000;
+/// Select bits of P1 with P15 low, which sends a 1 bit.
+const ONE_PULSE: u8 = 0b01_0000;
+/// Select bits of P1 with both P14 and P15 high, which must be written between pulses.
+const IDLE: u8 = 0b11_0000;
+
+/// Receives the packets that a game sends to the SGB through the select bits of the P1 register. A packet begins with
+/// a reset pulse (both select bits low) followed by 128 bits sent least significant bit first, with a 0 sent by
+/// pulsing P14 low and a 1 by pulsing P15 low. Both bits are returned high between pulses. The packet is terminated by
+/// a 0 stop bit.
+#[derive(Default)]
+pub struct PacketReceiver {
+    /// Number of bits received of the packet currently being transferred, or `None` if there is no transfer in
+    /// progress.
+    bits_received: Option<usize>,
+    data: Packet,
+    /// Whether both select bits have been returned high since the last pulse.
+    idle: bool,
+    /// The most recently received complete packet, cleared by [`PacketReceiver::take_packet`].
+    packet: Option<Packet>,
+}
+
+impl PacketReceiver {
+    pub fn new() -> Self {
+        PacketReceiver::default()
+    }
+
+    /// Handle a write of the given value to the select bits (4 and 5) of the P1 register.
+    pub fn write_select(&mut self, select: u8) {
+        match select {
+            RESET_PULSE => {
+                self.bits_received = Some(0);
+                self.data = [0; PACKET_SIZE];
+                self.idle = false;
+            }
+            IDLE => self.idle = true,
+            _ if self.idle => {
+                self.idle = false;
+                self.receive_bit(select == ONE_PULSE);
+            }
+            _ => {}
+        }
+    }
+
+    fn receive_bit(&mut self, bit: bool) {
+        let Some(count) = self.bits_received else {
+            return;
+        };
+
+        if count < PACKET_BITS {
+            self.data[count / 8] |= (bit as u8) << (count % 8);
+            self.bits_received = Some(count + 1);
+        } else {
+            if bit {
+                log::warn!("discarding SGB packet as its stop bit was not 0");
+            } else {
+                self.packet = Some(self.data);
+            }
+            self.bits_received = None;
+        }
+    }
+
+    /// Take the most recently received packet, should there be one.
+    pub fn take_packet(&mut self) -> Option<Packet> {
+        self.packet.take()
+    }
+}
+
+/// Send a packet through the P1 select bits by calling the given function for each write to P1 as a game would.
+#[cfg(test)]
+pub fn send_packet(packet: &Packet, mut write_select: impl FnMut(u8)) {
+    write_select(RESET_PULSE);
+    write_select(IDLE);
+
+    for index in 0..=PACKET_BITS {
+        let bit = index < PACKET_BITS && (packet[index / 8] >> (index % 8)) & 1 != 0;
+        write_select(if bit { ONE_PULSE } else { 0b10_0000 });
+        write_select(IDLE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receive() {
+        let packet: Packet = std::array::from_fn(|i| (i as u8).wrapping_mul(37));
+
+        let mut r = PacketReceiver::new();
+        send_packet(&packet, |select| r.write_select(select));
+        assert_eq!(r.take_packet(), Some(packet));
+        assert_eq!(r.take_packet(), None);
+    }
+
+    #[test]
+    fn pulses_outside_transfer_ignored() {
+        let mut r = PacketReceiver::new();
+        for _ in 0..200 {
+            r.write_select(ONE_PULSE);
+            r.write_select(IDLE);
+        }
+        assert_eq!(r.take_packet(), None);
+    }
+
+    #[test]
+    fn bad_stop_bit() {
+        let mut r = PacketReceiver::new();
+        r.write_select(RESET_PULSE);
+        r.write_select(IDLE);
+        for _ in 0..=PACKET_BITS {
+            r.write_select(ONE_PULSE);
+            r.write_select(IDLE);
+        }
+        assert_eq!(r.take_packet(), None);
+    }
+}
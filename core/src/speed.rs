@@ -0,0 +1,48 @@
+use crate::bits::{get_bit, modify_bit};
+
+/// Represents the CGB-only KEY1 register (0xFF4D) which is used to prepare a switch between normal and double speed
+/// modes. The switch itself is performed when the STOP instruction is executed.
+pub struct SpeedSwitch {
+    /// Whether the CPU is currently running in double speed mode.
+    pub double_speed: bool,
+    /// Whether a speed switch has been requested (set by writing to bit 0 of KEY1).
+    pub armed: bool,
+}
+
+impl SpeedSwitch {
+    pub fn new() -> Self {
+        SpeedSwitch {
+            double_speed: false,
+            armed: false,
+        }
+    }
+
+    pub fn get_byte(&self) -> u8 {
+        let b = modify_bit(0x7E, 7, self.double_speed);
+        modify_bit(b, 0, self.armed)
+    }
+
+    pub fn set_byte(&mut self, b: u8) {
+        self.armed = get_bit(b, 0);
+    }
+
+    /// Toggle between normal and double speed modes and disarm the switch.
+    pub fn switch(&mut self) {
+        self.double_speed = !self.double_speed;
+        self.armed = false;
+        log::debug!(
+            "switched to {} speed mode",
+            if self.double_speed {
+                "double"
+            } else {
+                "normal"
+            }
+        );
+    }
+}
+
+impl Default for SpeedSwitch {
+    fn default() -> Self {
+        SpeedSwitch::new()
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::parse::{self, ParseError};
+
+/// A label at an address in a particular bank of memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub bank: u16,
+    pub address: u16,
+    pub name: String,
+}
+
+/// Labels loaded from a symbol file, used to display and accept names in place of addresses when debugging.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    /// Symbols in the order they were added.
+    symbols: Vec<Symbol>,
+    /// Indices into `symbols` by (address, bank).
+    by_address: BTreeMap<(u16, u16), Vec<usize>>,
+    /// Indices into `symbols` by name.
+    by_name: HashMap<String, usize>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable::default()
+    }
+
+    /// Load symbols from an RGBDS or no$gmb symbol file (see [`SymbolTable::from_str`] for the format).
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        parse::from_file(path)
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Add a symbol. Should a symbol of the same name already exist, lookups by that name give the first one added.
+    pub fn add(&mut self, bank: u16, address: u16, name: impl Into<String>) {
+        let index = self.symbols.len();
+        let name = name.into();
+
+        self.by_address
+            .entry((address, bank))
+            .or_default()
+            .push(index);
+        self.by_name.entry(name.clone()).or_insert(index);
+        self.symbols.push(Symbol {
+            bank,
+            address,
+            name,
+        });
+    }
+
+    /// Find the symbol with the given name (case sensitive).
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.by_name.get(name).map(|&index| &self.symbols[index])
+    }
+
+    /// Get the name of the first symbol at the given address. The bank is only considered for addresses in switchable
+    /// ROM (0x4000 to 0x7FFF) as the banks mapped to other regions are not known.
+    pub fn label(&self, address: u16, bank: u16) -> Option<&str> {
+        self.at(address, bank)
+            .next()
+            .map(|symbol| symbol.name.as_str())
+    }
+
+    /// Find the closest symbol at or before the given address within the same region of memory, returning its name
+    /// and the offset of the address from it (e.g., `("Main", 3)` for an address 3 bytes after the label `Main`).
+    pub fn nearest(&self, address: u16, bank: u16) -> Option<(&str, u16)> {
+        self.by_address
+            .range((region_start(address), 0)..=(address, u16::MAX))
+            .rev()
+            .filter(|((_, symbol_bank), _)| !is_banked_rom(address) || *symbol_bank == bank)
+            .find_map(|(_, indices)| indices.first())
+            .map(|&index| {
+                let symbol = &self.symbols[index];
+                (symbol.name.as_str(), address - symbol.address)
+            })
+    }
+
+    /// Format an address as the name of the symbol at it (or the closest preceding symbol plus an offset), falling back
+    /// on the address in hexadecimal should there be no such symbol (e.g., `Main`, `Main+$3`, or `$0150`).
+    pub fn describe(&self, address: u16, bank: u16) -> String {
+        match self.nearest(address, bank) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) => format!("{name}+${offset:X}"),
+            None => format!("${address:04X}"),
+        }
+    }
+
+    fn at(&self, address: u16, bank: u16) -> impl Iterator<Item = &Symbol> + '_ {
+        self.by_address
+            .range((address, 0)..=(address, u16::MAX))
+            .filter(move |((_, symbol_bank), _)| !is_banked_rom(address) || *symbol_bank == bank)
+            .flat_map(|(_, indices)| indices.iter().map(|&index| &self.symbols[index]))
+    }
+}
+
+/// Find a symbol file beside the given ROM with the same name (e.g., `game.sym` for `game.gb`).
+pub fn find_symbols(rom_path: impl AsRef<Path>) -> Option<PathBuf> {
+    Some(rom_path.as_ref().with_extension("sym")).filter(|path| path.is_file())
+}
+
+fn is_banked_rom(address: u16) -> bool {
+    (0x4000..=0x7FFF).contains(&address)
+}
+
+/// The first address of the region of memory (ROM bank, VRAM, WRAM bank, etc.) containing the given address.
+fn region_start(address: u16) -> u16 {
+    match address {
+        0x0000..=0x3FFF => 0x0000,
+        0x4000..=0x7FFF => 0x4000,
+        0x8000..=0x9FFF => 0x8000,
+        0xA000..=0xBFFF => 0xA000,
+        0xC000..=0xCFFF => 0xC000,
+        0xD000..=0xDFFF => 0xD000,
+        0xE000..=0xFF7F => address,
+        0xFF80..=0xFFFF => 0xFF80,
+    }
+}
+
+/// Parses a symbol file as produced by RGBDS (`rgblink -n`) or used by no$gmb. Each line gives a bank and address in
+/// hexadecimal followed by a label. Anything following a `;` is a comment and blank lines are ignored. Lines in
+/// sections other than `[labels]` (as found in some no$gmb files) are skipped.
+///
+/// ```text
+/// ; File generated by rgblink
+/// 00:0150 Main
+/// 00:0158 Main.loop
+/// 01:4000 LoadTiles
+/// ```
+impl FromStr for SymbolTable {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut symbols = SymbolTable::new();
+        let mut in_labels = true;
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_labels = section.eq_ignore_ascii_case("labels");
+                continue;
+            }
+            if !in_labels {
+                continue;
+            }
+
+            let error = |reason: &str| ParseError {
+                line: index + 1,
+                reason: format!("invalid symbol '{line}': {reason}"),
+            };
+
+            let (location, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| error("expected a bank and address followed by a label"))?;
+            let (bank, address) = location
+                .split_once(':')
+                .ok_or_else(|| error("expected the bank and address to be separated by ':'"))?;
+            let bank = u16::from_str_radix(bank, 16).map_err(|_| error("invalid bank"))?;
+            let address = u16::from_str_radix(address, 16).map_err(|_| error("invalid address"))?;
+
+            symbols.add(bank, address, name.trim());
+        }
+
+        Ok(symbols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RGBDS: &str = "; File generated by rgblink
+00:0000 RST_00
+00:0150 Main
+00:0150 EntryPoint
+00:0158 Main.loop
+01:4000 LoadTiles
+02:4000 PlaySound
+00:c000 wBuffer
+";
+
+    #[test]
+    fn parse() {
+        let symbols: SymbolTable = RGBDS.parse().unwrap();
+        assert_eq!(symbols.symbols().len(), 7);
+        assert_eq!(
+            symbols.get("wBuffer"),
+            Some(&Symbol {
+                bank: 0,
+                address: 0xC000,
+                name: "wBuffer".to_string()
+            })
+        );
+        assert!(symbols.get("main").is_none());
+
+        let no_gmb = "[labels]\n01:4000 LoadTiles ; comment\n[definitions]\nnonsense\n";
+        assert_eq!(no_gmb.parse::<SymbolTable>().unwrap().symbols().len(), 1);
+
+        let err = "00:0150 Main\n0150 Bad".parse::<SymbolTable>().unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!("zz:0150 Bad".parse::<SymbolTable>().is_err());
+    }
+
+    #[test]
+    fn lookup() {
+        let symbols: SymbolTable = RGBDS.parse().unwrap();
+
+        assert_eq!(symbols.label(0x0150, 0), Some("Main"));
+        assert_eq!(symbols.label(0x0151, 0), None);
+        assert_eq!(symbols.label(0x4000, 1), Some("LoadTiles"));
+        assert_eq!(symbols.label(0x4000, 2), Some("PlaySound"));
+        assert_eq!(symbols.label(0x4000, 3), None);
+        assert_eq!(symbols.label(0xC000, 5), Some("wBuffer")); // bank ignored outside of ROM
+
+        assert_eq!(symbols.nearest(0x015A, 0), Some(("Main.loop", 2)));
+        assert_eq!(symbols.nearest(0x4010, 2), Some(("PlaySound", 0x10)));
+        assert_eq!(symbols.nearest(0x4010, 3), None);
+        assert_eq!(symbols.nearest(0xD000, 1), None); // different region to wBuffer
+
+        assert_eq!(symbols.describe(0x0150, 0), "Main");
+        assert_eq!(symbols.describe(0x0003, 0), "RST_00+$3");
+        assert_eq!(symbols.describe(0x8000, 0), "$8000");
+    }
+}
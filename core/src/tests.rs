@@ -1,7 +1,480 @@
 use super::*;
 
 const CYCLES_WITHOUT_LOG_THRESHOLD: usize = 10_000_000;
+const MAX_STEPS: usize = 100_000_000;
 
+/// Value at 0xA000 while a Blargg test ROM reporting its result via memory is still running.
+const MEMORY_OUTPUT_RUNNING: u8 = 0x80;
+/// Signature written at 0xA001-0xA003 by Blargg test ROMs that report their result via memory.
+const MEMORY_OUTPUT_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+
+/// Registers B, C, D, E, H, and L hold the first few Fibonacci numbers after a Mooneye test ROM passes.
+const MOONEYE_PASS_REGISTERS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+/// Mooneye test ROMs execute the LD B, B instruction once they are finished.
+const MOONEYE_FINISHED_OPCODE: u8 = 0x40;
+
+fn load_rom(rom: &[u8]) -> GameBoy {
+    let cart = cartridge::Cartridge::from_data(rom.to_vec());
+    let mbc = mbc::from_cartridge(cart).unwrap();
+    GameBoy::new(mbc)
+}
+
+/// Build a 32 KiB MBC1 cartridge with 8 KiB of RAM whose ROM executes NOPs from the entry point until reaching an
+/// infinite loop at 0x0150.
+fn mbc1_ram_cartridge() -> Box<dyn mbc::MemoryBankController> {
+    let mut rom = vec![0; 0x8000];
+    rom[0x147] = 0x03; // MBC1 + RAM + battery
+    rom[0x149] = 0x02; // 8 KiB RAM
+    rom[0x150..0x152].copy_from_slice(&[0x18, 0xFE]); // JR -2
+    mbc::from_cartridge(cartridge::Cartridge::from_data(rom)).unwrap()
+}
+
+/// Build a 32 KiB cartridge (without an MBC) that executes the given code from the entry point.
+fn cartridge_with_code(code: &[u8]) -> Box<dyn mbc::MemoryBankController> {
+    let mut rom = vec![0; 0x8000];
+    rom[0x100..0x100 + code.len()].copy_from_slice(code);
+    mbc::from_cartridge(cartridge::Cartridge::from_data(rom)).unwrap()
+}
+
+/// Execute a single step, failing immediately should the CPU lock up due to an illegal opcode.
+fn step(gb: &mut GameBoy, file: &str) {
+    gb.step();
+
+    if let cpu::State::Locked { address, opcode } = gb.cpu.state() {
+        panic!("test ROM \"{file}\" locked up the CPU by executing illegal opcode {opcode:#04X} at {address:#06X}");
+    }
+}
+
+#[test]
+fn reset_keeps_cartridge_ram() {
+    let mut gb = GameBoy::new(mbc1_ram_cartridge());
+    gb.bus.write8(0x0000, 0x0A); // enable cartridge RAM
+    gb.bus.write8(0xA000, 0x42);
+    gb.bus.write8(0xC000, 0x42);
+    gb.step();
+
+    gb.reset();
+    assert_eq!(gb.cpu.regs.pc, 0x0100);
+    assert_eq!(gb.bus.read8(0xC000), 0);
+    assert_eq!(gb.bus.read8(0xA000), 0xFF); // cartridge RAM disabled again by reset
+
+    gb.bus.write8(0x0000, 0x0A);
+    assert_eq!(gb.bus.read8(0xA000), 0x42);
+}
+
+#[test]
+fn swap_cartridge() {
+    let mut gb = GameBoy::new(mbc1_ram_cartridge());
+    gb.bus.write8(0x0000, 0x0A);
+    gb.bus.write8(0xA000, 0x42);
+
+    let mut previous = gb.swap_cartridge(mbc1_ram_cartridge());
+    gb.bus.write8(0x0000, 0x0A);
+    assert_eq!(gb.bus.read8(0xA000), 0);
+
+    assert_eq!(previous.ram_mut().unwrap()[0], 0x42);
+}
+
+#[test]
+fn run_frame() {
+    let mut gb = GameBoy::new(mbc1_ram_cartridge());
+
+    // the first frame is partial as the PPU starts part-way through VBlank
+    gb.run_frame();
+
+    for _ in 0..3 {
+        let cycles = gb.run_frame();
+        assert!((CYCLES_PER_FRAME..CYCLES_PER_FRAME + 12).contains(&cycles));
+    }
+}
+
+#[test]
+fn run_cycles() {
+    let mut gb = GameBoy::new(mbc1_ram_cartridge());
+
+    // the ROM is all NOPs so each step takes 4 cycles - running 6 cycles overshoots by 2
+    assert_eq!(gb.run_cycles(6), 8);
+    assert_eq!(gb.run_cycles(2), 0);
+    assert_eq!(gb.run_cycles(5), 8);
+    assert_eq!(gb.run_cycles(3), 0);
+}
+
+#[test]
+fn update_double_speed() {
+    // DIV is incremented every 256 CPU cycles, so twice as often in real time when running at double speed
+    let mut gb = GameBoy::with_model(cartridge_with_code(&[0x18, 0xFE]), Model::Cgb); // JR -2
+    gb.bus.reset_divider();
+    gb.update(1.0 / 256.0);
+    assert_eq!(gb.bus.read8(0xFF04), 64);
+
+    let mut gb = GameBoy::with_model(cartridge_with_code(&[0x18, 0xFE]), Model::Cgb);
+    gb.bus.switch_speed();
+    gb.bus.reset_divider();
+    gb.update(1.0 / 256.0);
+    assert_eq!(gb.bus.read8(0xFF04), 128);
+}
+
+#[test]
+fn stop_speed_switch() {
+    let mut gb = GameBoy::with_model(
+        cartridge_with_code(&[
+            0x06, 0x00, // LD B, 0
+            0x05, // DEC B
+            0x20, 0xFD, // JR NZ, -3
+            0x3E, 0x01, // LD A, 1
+            0xE0, 0x4D, // LDH (0x4D), A
+            0x10, 0x00, // STOP
+            0x18, 0xFE, // JR -2
+        ]),
+        Model::Cgb,
+    );
+
+    while gb.cpu.regs.pc != 0x0109 {
+        gb.step();
+    }
+    assert!(gb.bus.speed_switch_armed());
+    assert_eq!(gb.bus.read8(0xFF4D), 0x7F);
+    let div = gb.bus.read8(0xFF04);
+    assert!(div > 0x10);
+
+    gb.step(); // STOP
+    assert_eq!(gb.cpu.regs.pc, 0x010B);
+    assert_eq!(gb.cpu.state(), cpu::State::Running);
+    assert!(gb.bus.double_speed());
+    assert_eq!(gb.bus.read8(0xFF4D), 0xFE);
+    // DIV was reset and then incremented during the pause while the clock stabilised
+    assert!(gb.bus.read8(0xFF04) < div);
+}
+
+#[test]
+fn stop_mode() {
+    let mut gb = GameBoy::new(cartridge_with_code(&[
+        0x3E, 0x10, // LD A, 0x10 (select action buttons)
+        0xE0, 0x00, // LDH (0x00), A
+        0x10, 0x00, // STOP
+        0x3C, // INC A
+        0x18, 0xFE, // JR -2
+    ]));
+
+    while gb.cpu.regs.pc != 0x0104 {
+        gb.step();
+    }
+    gb.step(); // STOP
+    assert_eq!(gb.cpu.state(), cpu::State::Stopped);
+    assert_eq!(gb.cpu.regs.pc, 0x0106);
+    assert_eq!(gb.bus.read8(0xFF04), 0);
+
+    // the system clock is stopped so nothing happens until a button is pressed
+    gb.run_cycles(CYCLES_PER_FRAME);
+    assert_eq!(gb.cpu.state(), cpu::State::Stopped);
+    assert_eq!(gb.bus.read8(0xFF04), 0);
+    assert_eq!(gb.cpu.regs.a, 0x10);
+
+    gb.bus.joypad.set_button(joypad::Button::A, true);
+    gb.step();
+    assert_eq!(gb.cpu.state(), cpu::State::Running);
+    assert_eq!(gb.cpu.regs.a, 0x11);
+}
+
+#[test]
+fn cheats() {
+    let mut gb = GameBoy::new(mbc1_ram_cartridge());
+    gb.bus.cheats = "01FF34C2\n3EF-0EF".parse().unwrap();
+    assert_eq!(gb.bus.read8(0x0F0E), 0x3E);
+
+    gb.run_frame();
+    assert_eq!(gb.bus.read8(0xC234), 0xFF);
+
+    gb.bus.write8(0xC234, 0x00);
+    gb.bus.cheats.set_enabled(0, false);
+    gb.run_frame();
+    assert_eq!(gb.bus.read8(0xC234), 0x00);
+
+    // written to cartridge RAM bank 0 even though RAM is disabled, while a write to a missing bank is ignored
+    gb.bus.cheats = "80AB34A2\n81CD34A2".parse().unwrap();
+    gb.run_frame();
+    assert_eq!(gb.bus.read8(0xA234), 0xFF);
+    gb.bus.write8(0x0000, 0x0A); // enable cartridge RAM
+    assert_eq!(gb.bus.read8(0xA234), 0xAB);
+}
+
+#[test]
+fn patch_beside_rom() {
+    let dir = std::env::temp_dir().join(format!("rustyboy-patch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let rom_path = dir.join("game.gb");
+    std::fs::write(&rom_path, [0; 8]).unwrap();
+
+    assert_eq!(patch::find_patch(&rom_path), None);
+    std::fs::write(dir.join("other.ips"), b"PATCH\0\0\0\0\x01\xAAEOF").unwrap();
+    assert_eq!(patch::find_patch(&rom_path), None);
+
+    std::fs::write(dir.join("game.ips"), b"PATCH\0\0\x02\0\x01\xBBEOF").unwrap();
+    assert_eq!(patch::find_patch(&rom_path), Some(dir.join("game.ips")));
+
+    let mut cart = cartridge::Cartridge::from_file(&rom_path).unwrap();
+    cart.apply_patch_beside(&rom_path).unwrap();
+    assert_eq!(cart.read8(0x00), 0x00);
+    assert_eq!(cart.read8(0x02), 0xBB);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn breakpoints() {
+    use debugger::{BreakReason, Breakpoint};
+
+    let mut gb = GameBoy::new(mbc1_ram_cartridge());
+    gb.bus.debugger.add_breakpoint(Breakpoint::new(0x0110));
+    gb.bus
+        .debugger
+        .add_breakpoint(Breakpoint::with_bank(0x0120, 1)); // bank 0 is mapped there
+
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::Breakpoint(0x0110)
+    );
+    assert_eq!(gb.cpu.regs.pc, 0x0110);
+
+    // resuming executes the instruction at the breakpoint
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::CycleLimit
+    );
+    assert_eq!(gb.cpu.regs.pc, 0x0150);
+
+    // a breakpoint at the initial PC is triggered
+    let mut gb = GameBoy::new(mbc1_ram_cartridge());
+    gb.bus.debugger.add_breakpoint(Breakpoint::new(0x0100));
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::Breakpoint(0x0100)
+    );
+}
+
+#[test]
+fn breakpoint_after_watchpoint() {
+    use debugger::{Access, BreakReason, Breakpoint, Watchpoint};
+
+    let mut gb = GameBoy::new(cartridge_with_code(&[
+        0x3E, 0x42, // LD A, 0x42
+        0xEA, 0x00, 0xC0, // LD (0xC000), A
+        0x00, // NOP
+        0x18, 0xFE, // JR -2
+    ]));
+    gb.bus
+        .debugger
+        .add_watchpoint(Watchpoint::new(0xC000..=0xC000, Access::Write));
+    gb.bus.debugger.add_breakpoint(Breakpoint::new(0x0105));
+
+    assert!(matches!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::Watchpoint { .. }
+    ));
+    assert_eq!(gb.cpu.regs.pc, 0x0105);
+    assert_eq!(gb.bus.debugger.breakpoints()[0].hits, 0);
+
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::Breakpoint(0x0105)
+    );
+    assert_eq!(gb.bus.debugger.breakpoints()[0].hits, 1);
+}
+
+#[test]
+fn watchpoints() {
+    use debugger::{Access, BreakReason, Watchpoint};
+
+    let mut gb = GameBoy::new(cartridge_with_code(&[
+        0x3E, 0x42, // LD A, 0x42
+        0xEA, 0x00, 0xC0, // LD (0xC000), A
+        0xFA, 0x00, 0xC0, // LD A, (0xC000)
+        0xE0, 0x40, // LDH (0x40), A
+        0x18, 0xFE, // JR -2
+    ]));
+    gb.bus
+        .debugger
+        .add_watchpoint(Watchpoint::new(0xC000..=0xC0FF, Access::Write));
+    gb.bus
+        .debugger
+        .add_watchpoint(Watchpoint::new(0xC000..=0xC000, Access::Read).with_value(0x42));
+    gb.bus
+        .debugger
+        .add_watchpoint(Watchpoint::io_register("LCDC", Access::Write).unwrap());
+    gb.bus
+        .debugger
+        .add_watchpoint(Watchpoint::new(0x010A..=0x010A, Access::Execute));
+
+    let write = BreakReason::Watchpoint {
+        address: 0xC000,
+        access: Access::Write,
+        value: 0x42,
+    };
+    assert_eq!(gb.run_until_break(CYCLES_PER_FRAME), write);
+    assert_eq!(gb.cpu.regs.pc, 0x0105);
+
+    let read = BreakReason::Watchpoint {
+        address: 0xC000,
+        access: Access::Read,
+        value: 0x42,
+    };
+    assert_eq!(gb.run_until_break(CYCLES_PER_FRAME), read);
+
+    let lcdc = gb.run_until_break(CYCLES_PER_FRAME);
+    assert_eq!(lcdc.to_string(), "write watchpoint on LCDC (value 0x42)");
+
+    let execute = gb.run_until_break(CYCLES_PER_FRAME);
+    assert_eq!(
+        execute,
+        BreakReason::Watchpoint {
+            address: 0x010A,
+            access: Access::Execute,
+            value: 0x18,
+        }
+    );
+}
+
+#[test]
+fn conditional_breakpoints() {
+    use debugger::{expression::Message, BreakReason, Breakpoint, HitAction};
+
+    let mut gb = GameBoy::new(cartridge_with_code(&[
+        0x3E, 0x00, // LD A, 0
+        0x3C, // INC A
+        0x18, 0xFD, // JR -3
+    ]));
+    gb.bus
+        .debugger
+        .add_breakpoint(Breakpoint::new(0x0103).with_condition("A == 5".parse().unwrap()));
+    let message = Message::parse("A={A}", &gb.bus.symbols).unwrap();
+    gb.bus
+        .debugger
+        .add_breakpoint(Breakpoint::new(0x0102).with_action(HitAction::Log(message)));
+
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::Breakpoint(0x0103)
+    );
+    assert_eq!(gb.cpu.regs.a, 5);
+
+    let hits: Vec<u64> = gb
+        .bus
+        .debugger
+        .breakpoints()
+        .iter()
+        .map(|b| b.hits)
+        .collect();
+    assert_eq!(hits, [1, 5]);
+    let log = gb.bus.debugger.take_log();
+    assert_eq!(log.len(), 5);
+    assert_eq!(log[4], "breakpoint at 0x0102: A=$04");
+}
+
+#[test]
+fn call_stack() {
+    use debugger::call_stack::{FrameKind, StackImbalance};
+    use debugger::{BreakReason, Breakpoint};
+
+    let mut gb = GameBoy::new(cartridge_with_code(&[
+        0xCD, 0x09, 0x01, // CALL 0x0109
+        0xCD, 0x10, 0x01, // CALL 0x0110
+        0x18, 0xFE, // JR -2
+        0x00, // NOP
+        0xCD, 0x0D, 0x01, // 0x0109: CALL 0x010D
+        0xC9, // RET
+        0x00, // 0x010D: NOP
+        0xC9, // RET
+        0x00, // NOP
+        0xE1, // 0x0110: POP HL
+        0x23, // INC HL
+        0xE5, // PUSH HL
+        0xC9, // RET
+    ]));
+    gb.bus.debugger.add_breakpoint(Breakpoint::new(0x010D));
+    gb.bus.debugger.set_break_on_stack_imbalance(true);
+
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::Breakpoint(0x010D)
+    );
+    let backtrace: Vec<(u16, u16)> = gb
+        .cpu
+        .call_stack()
+        .backtrace()
+        .map(|frame| (frame.target, frame.return_address))
+        .collect();
+    assert_eq!(backtrace, [(0x010D, 0x010C), (0x0109, 0x0103)]);
+    assert_eq!(gb.cpu.call_stack().frames()[0].kind, FrameKind::Call);
+
+    // the second subroutine returns past the JR instruction by incrementing its return address
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::StackImbalance(StackImbalance {
+            address: 0x0107,
+            sp: 0xFFFC,
+            expected: Some(0x0106),
+        })
+    );
+    assert_eq!(gb.cpu.regs.pc, 0x0107);
+    assert_eq!(gb.cpu.call_stack().depth(), 0);
+}
+
+#[test]
+fn interrupt_breakpoint() {
+    use debugger::BreakReason;
+    use interrupts::Interrupt;
+
+    let mut gb = GameBoy::new(cartridge_with_code(&[
+        0x3E, 0x01, // LD A, 1
+        0xE0, 0xFF, // LDH (0xFF), A
+        0xFB, // EI
+        0x18, 0xFE, // JR -2
+    ]));
+    gb.bus
+        .debugger
+        .set_interrupt_breakpoint(Interrupt::VBlank, true);
+
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME * 2),
+        BreakReason::Interrupt(Interrupt::VBlank)
+    );
+    assert_eq!(gb.cpu.regs.pc, 0x0040);
+
+    // a breakpoint on the handler is still triggered after stopping for the interrupt
+    gb.bus
+        .debugger
+        .add_breakpoint(debugger::Breakpoint::new(0x0040));
+    assert_eq!(
+        gb.run_until_break(CYCLES_PER_FRAME),
+        BreakReason::Breakpoint(0x0040)
+    );
+}
+
+#[test]
+fn sgb_multiplayer() {
+    let mut gb = GameBoy::with_model(mbc1_ram_cartridge(), Model::Sgb);
+    gb.bus
+        .joypad
+        .set_player_button(1, joypad::Button::Start, true);
+
+    // MLT_REQ requesting 2 players
+    let mut packet = [0; sgb::PACKET_SIZE];
+    packet[..2].copy_from_slice(&[0x89, 0x01]);
+    sgb::send_packet(&packet, |select| gb.bus.write8(0xFF00, select));
+    assert_eq!(gb.bus.sgb.as_ref().unwrap().players(), 2);
+
+    // returning P1 to idle after the final bit of the packet already moved on to the second joypad
+    assert_eq!(gb.bus.read8(0xFF00) & 0xF, 0xE);
+    gb.bus.write8(0xFF00, 0x10);
+    assert_eq!(gb.bus.read8(0xFF00) & 0xF, 0b0111);
+
+    gb.bus.write8(0xFF00, 0x30);
+    assert_eq!(gb.bus.read8(0xFF00) & 0xF, 0xF);
+    gb.bus.write8(0xFF00, 0x10);
+    assert_eq!(gb.bus.read8(0xFF00) & 0xF, 0xF);
+}
+
+/// Blargg test ROMs that report their result by writing text to serial out.
 macro_rules! test_rom {
     ($name:ident, $file:literal) => {
         #[test]
@@ -11,17 +484,14 @@ macro_rules! test_rom {
                 "/../gb-test-roms/",
                 $file
             ));
-            let cart = cartridge::Cartridge::from_data(rom.to_vec());
-            let mbc = mbc::from_cartridge(cart).unwrap();
-
-            let mut gb = GameBoy::new(mbc);
+            let mut gb = load_rom(rom);
 
             let mut logged = String::new();
             let mut cycles_since_last_log = 0;
 
             // continue executing instructions until enough cycles have passed without any output being produced
             while cycles_since_last_log < CYCLES_WITHOUT_LOG_THRESHOLD {
-                gb.step();
+                step(&mut gb, $file);
 
                 cycles_since_last_log += 1;
 
@@ -43,6 +513,94 @@ macro_rules! test_rom {
     };
 }
 
+/// Blargg test ROMs that report their result by writing a status code and text to cartridge RAM (starting at 0xA000)
+/// rather than to serial out.
+macro_rules! test_rom_memory_output {
+    ($name:ident, $file:literal) => {
+        #[test]
+        fn $name() {
+            let rom = include_bytes!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../gb-test-roms/",
+                $file
+            ));
+            let mut gb = load_rom(rom);
+
+            let finished = |gb: &GameBoy| {
+                let signature = [0xA001, 0xA002, 0xA003].map(|addr| gb.bus.read8(addr));
+                signature == MEMORY_OUTPUT_SIGNATURE
+                    && gb.bus.read8(0xA000) != MEMORY_OUTPUT_RUNNING
+            };
+
+            let mut steps = 0;
+            while !finished(&gb) {
+                assert!(steps < MAX_STEPS, "Blargg test ROM \"{}\" timed out", $file);
+                step(&mut gb, $file);
+                steps += 1;
+            }
+
+            let logged: String = (0xA004..)
+                .map(|addr| gb.bus.read8(addr))
+                .take_while(|b| *b != 0)
+                .map(|b| b as char)
+                .collect();
+
+            assert_eq!(
+                gb.bus.read8(0xA000),
+                0,
+                "Blargg test ROM \"{}\" failed: {}",
+                $file,
+                logged.trim().replace("\n", " ")
+            );
+
+            log::info!("{} - \"{}\"", stringify!($name), logged.trim());
+        }
+    };
+}
+
+/// Mooneye test ROMs that report their result through the values of the CPU registers.
+macro_rules! mooneye_test_rom {
+    ($name:ident, $file:literal) => {
+        #[test]
+        #[ignore = "requires mooneye-test-suite to be built"]
+        fn $name() {
+            // the Mooneye ROMs must be built from source (requiring WLA-DX) so are loaded at runtime rather than
+            // included, with the tests ignored unless requested so that the other tests can run without them
+            let path = concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../mooneye-test-suite/build/",
+                $file
+            );
+            let rom = std::fs::read(path).unwrap_or_else(|e| {
+                panic!(
+                    "failed to read Mooneye test ROM \"{}\" (has mooneye-test-suite been built?): {e}",
+                    $file
+                )
+            });
+            let mut gb = load_rom(&rom);
+
+            let mut steps = 0;
+            while gb.bus.read8(gb.cpu.regs.pc) != MOONEYE_FINISHED_OPCODE {
+                assert!(
+                    steps < MAX_STEPS,
+                    "Mooneye test ROM \"{}\" timed out",
+                    $file
+                );
+                step(&mut gb, $file);
+                steps += 1;
+            }
+
+            let regs = &gb.cpu.regs;
+            assert_eq!(
+                [regs.b, regs.c, regs.d, regs.e, regs.h, regs.l],
+                MOONEYE_PASS_REGISTERS,
+                "Mooneye test ROM \"{}\" failed",
+                $file
+            );
+        }
+    };
+}
+
 test_rom!(cpu_instrs_special, "cpu_instrs/individual/01-special.gb");
 test_rom!(
     cpu_instrs_interrupts,
@@ -62,3 +620,57 @@ test_rom!(cpu_instrs_bit_ops, "cpu_instrs/individual/10-bit ops.gb");
 test_rom!(cpu_instrs_op_a_hl, "cpu_instrs/individual/11-op a,(hl).gb");
 
 test_rom!(instr_timing, "instr_timing/instr_timing.gb");
+
+test_rom!(mem_timing_read, "mem_timing/individual/01-read_timing.gb");
+test_rom!(mem_timing_write, "mem_timing/individual/02-write_timing.gb");
+test_rom!(
+    mem_timing_modify,
+    "mem_timing/individual/03-modify_timing.gb"
+);
+
+test_rom_memory_output!(
+    mem_timing_2_read,
+    "mem_timing-2/rom_singles/01-read_timing.gb"
+);
+test_rom_memory_output!(
+    mem_timing_2_write,
+    "mem_timing-2/rom_singles/02-write_timing.gb"
+);
+test_rom_memory_output!(
+    mem_timing_2_modify,
+    "mem_timing-2/rom_singles/03-modify_timing.gb"
+);
+
+test_rom_memory_output!(halt_bug, "halt_bug.gb");
+
+test_rom_memory_output!(oam_bug_lcd_sync, "oam_bug/rom_singles/1-lcd_sync.gb");
+test_rom_memory_output!(oam_bug_causes, "oam_bug/rom_singles/2-causes.gb");
+test_rom_memory_output!(oam_bug_non_causes, "oam_bug/rom_singles/3-non_causes.gb");
+test_rom_memory_output!(
+    oam_bug_scanline_timing,
+    "oam_bug/rom_singles/4-scanline_timing.gb"
+);
+test_rom_memory_output!(oam_bug_timing_bug, "oam_bug/rom_singles/5-timing_bug.gb");
+test_rom_memory_output!(
+    oam_bug_timing_no_bug,
+    "oam_bug/rom_singles/6-timing_no_bug.gb"
+);
+test_rom_memory_output!(
+    oam_bug_timing_effect,
+    "oam_bug/rom_singles/7-timing_effect.gb"
+);
+test_rom_memory_output!(
+    oam_bug_instr_effect,
+    "oam_bug/rom_singles/8-instr_effect.gb"
+);
+
+mooneye_test_rom!(ie_push, "acceptance/interrupts/ie_push.gb");
+
+mooneye_test_rom!(oam_dma_basic, "acceptance/oam_dma/basic.gb");
+mooneye_test_rom!(oam_dma_reg_read, "acceptance/oam_dma/reg_read.gb");
+mooneye_test_rom!(oam_dma_sources, "acceptance/oam_dma/sources-GS.gb");
+mooneye_test_rom!(oam_dma_restart, "acceptance/oam_dma_restart.gb");
+mooneye_test_rom!(oam_dma_start, "acceptance/oam_dma_start.gb");
+mooneye_test_rom!(oam_dma_timing, "acceptance/oam_dma_timing.gb");
+
+mooneye_test_rom!(unused_hwio, "acceptance/bits/unused_hwio-GS.gb");
@@ -46,6 +46,12 @@ impl Timer {
         log::trace!("timer updated - {self:?}");
     }
 
+    /// Reset the divider to 0 as happens when the STOP instruction is executed.
+    pub fn reset_divider(&mut self) {
+        self.divider = 0;
+        self.divider_cycles = 0;
+    }
+
     fn enabled(&self) -> bool {
         get_bit(self.control, 2)
     }
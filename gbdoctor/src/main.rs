@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::{env, io::Write};
 
-use rustyboy_core::{cartridge::Cartridge, mbc, GameBoy};
+use rustyboy_core::{cartridge::Cartridge, cpu::State, mbc, GameBoy};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -19,6 +19,13 @@ fn main() {
         loop {
             gb.step();
 
+            if let State::Locked { address, opcode } = gb.cpu.state() {
+                println!(
+                    "CPU locked up due to illegal opcode {opcode:#04X} at address {address:#06X}"
+                );
+                break;
+            }
+
             writeln!(
                 file,
                 "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
@@ -0,0 +1,745 @@
+use rustyboy_core::{
+    cpu::{disassembler, State},
+    debugger::{
+        self,
+        expression::{Expression, Message},
+        Access, BreakReason, Breakpoint, HitAction, Watchpoint,
+    },
+    interrupts::Interrupt,
+    symbols::SymbolTable,
+    GameBoy,
+};
+
+use crossterm::{cursor, event::KeyCode, style, terminal, QueueableCommand};
+
+use std::io::{Stdout, Write};
+
+const DISASSEMBLY_WIDTH: u16 = 44;
+const DISASSEMBLY_LINES: u16 = 16;
+const SIDE_X: u16 = DISASSEMBLY_WIDTH + 2;
+const SIDE_WIDTH: u16 = 28;
+const REGISTER_LINES: u16 = 8;
+const MEMORY_Y: u16 = DISASSEMBLY_LINES + 1;
+const MEMORY_LINES: u16 = 8;
+const OUTPUT_Y: u16 = MEMORY_Y + MEMORY_LINES + 1;
+const OUTPUT_LINES: usize = 8;
+const WIDTH: u16 = SIDE_X + SIDE_WIDTH;
+
+const INTERRUPTS: [(&str, Interrupt); 5] = [
+    ("vblank", Interrupt::VBlank),
+    ("stat", Interrupt::LcdStat),
+    ("timer", Interrupt::Timer),
+    ("serial", Interrupt::Serial),
+    ("joypad", Interrupt::Joypad),
+];
+
+const HELP: [&str; 8] = [
+    "break [ADDR | BANK:ADDR | INTERRUPT | stack] [if EXPR]  (no argument lists breakpoints)",
+    "watch TARGET [r|w|x] [VALUE] [if EXPR]  (TARGET: ADDR, ADDR-ADDR, or IO register)",
+    "trace ADDR \"MESSAGE\" [if EXPR]  (logs MESSAGE with {EXPR} replaced by its value)",
+    "delete [N]  (no argument deletes all breakpoints and watchpoints)",
+    "step [N], continue, backtrace, print REG|ADDR|EXPR, memory ADDR",
+    "Addresses are labels or hex, numbers in EXPR are decimal unless prefixed with $ or 0x",
+    "Enter repeats the last command, PageUp/PageDown scroll the memory view",
+    "Tab resumes execution, Esc quits",
+];
+
+/// What the emulator should do after the debug view handles a key press.
+pub enum Action {
+    /// Remain paused in the debug view.
+    Stay,
+    /// Leave the debug view and resume execution.
+    Continue,
+}
+
+/// Debug mode of the terminal frontend. While active, emulation is paused and the view is split into panes showing
+/// the disassembly around PC, the CPU registers, the stack, and a region of memory, along with a command line.
+pub struct DebugView {
+    command: String,
+    last_command: String,
+    output: Vec<String>,
+    /// Address of the first instruction shown in the disassembly pane.
+    disassembly_start: u16,
+    /// Address of the first byte shown in the memory pane.
+    memory_start: u16,
+}
+
+impl DebugView {
+    pub fn new() -> Self {
+        DebugView {
+            command: String::new(),
+            last_command: String::new(),
+            output: vec!["Type 'help' for a list of commands".to_string()],
+            disassembly_start: 0,
+            memory_start: 0xC000,
+        }
+    }
+
+    /// Called when execution is paused, with the reason execution stopped (`None` if paused by the user).
+    pub fn enter(&mut self, gb: &mut GameBoy, reason: Option<BreakReason>) {
+        self.print_log(gb);
+
+        let pc = gb.cpu.regs.pc;
+        let location = if gb.bus.symbols.is_empty() {
+            String::new()
+        } else {
+            format!(" in {}", gb.bus.symbols.describe(pc, gb.bus.rom_bank(pc)))
+        };
+        match reason {
+            Some(reason) => self.print(format!("Stopped{location}: {reason}")),
+            None => self.print(format!("Paused{location}")),
+        }
+
+        let call_stack = gb.cpu.call_stack();
+        if call_stack.depth() > 0 {
+            let callers: Vec<String> = call_stack
+                .backtrace()
+                .map(|frame| {
+                    gb.bus
+                        .symbols
+                        .describe(frame.return_address, frame.return_bank)
+                })
+                .collect();
+            self.print(format!("Called from {}", callers.join(" < ")));
+        }
+        self.disassembly_start = pc;
+    }
+
+    pub fn handle_key(&mut self, gb: &mut GameBoy, code: KeyCode) -> Action {
+        match code {
+            KeyCode::Char(c) => self.command.push(c),
+            KeyCode::Backspace => {
+                self.command.pop();
+            }
+            KeyCode::PageUp => self.memory_start = self.memory_start.wrapping_sub(0x80),
+            KeyCode::PageDown => self.memory_start = self.memory_start.wrapping_add(0x80),
+            KeyCode::Tab => return Action::Continue,
+            KeyCode::Enter => {
+                let mut command = std::mem::take(&mut self.command);
+                if command.trim().is_empty() {
+                    command = self.last_command.clone();
+                }
+                self.print(format!("> {command}"));
+                let action = self.execute(gb, &command);
+                self.print_log(gb);
+                self.last_command = command;
+                return action;
+            }
+            _ => {}
+        }
+        Action::Stay
+    }
+
+    fn execute(&mut self, gb: &mut GameBoy, command: &str) -> Action {
+        let (command, condition) = split_condition(command);
+        let condition = match condition
+            .map(|condition| Expression::parse(condition, &gb.bus.symbols))
+            .transpose()
+        {
+            Ok(condition) => condition,
+            Err(e) => {
+                self.print(format!("invalid condition {e}"));
+                return Action::Stay;
+            }
+        };
+
+        let command = command.trim();
+        if command.is_empty() {
+            return Action::Stay;
+        }
+        let (name, rest) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let args: Vec<&str> = rest.split_whitespace().collect();
+
+        let result = match name {
+            "break" | "b" => self.command_break(gb, &args, condition),
+            "watch" | "w" => self.command_watch(gb, &args, condition),
+            "trace" | "t" => self.command_trace(gb, rest, condition),
+            "delete" | "d" => self.command_delete(gb, &args),
+            "step" | "s" => self.command_step(gb, &args),
+            "continue" | "c" => return Action::Continue,
+            "backtrace" | "bt" => {
+                self.print_backtrace(gb);
+                Ok(())
+            }
+            "print" | "p" => self.command_print(gb, &args),
+            "memory" | "m" => args
+                .first()
+                .and_then(|arg| parse_address(gb, arg))
+                .map(|addr| self.memory_start = addr & 0xFFF0)
+                .ok_or_else(|| "usage: memory ADDR".to_string()),
+            "help" | "h" => {
+                HELP.iter().for_each(|line| self.print(line.to_string()));
+                Ok(())
+            }
+            _ => Err(format!("unknown command '{name}'")),
+        };
+
+        if let Err(msg) = result {
+            self.print(msg);
+        }
+        Action::Stay
+    }
+
+    fn command_break(
+        &mut self,
+        gb: &mut GameBoy,
+        args: &[&str],
+        condition: Option<Expression>,
+    ) -> Result<(), String> {
+        let Some(arg) = args.first() else {
+            self.list_breakpoints(gb);
+            return Ok(());
+        };
+
+        if let Some((_, int)) = INTERRUPTS
+            .iter()
+            .find(|(name, _)| arg.eq_ignore_ascii_case(name))
+        {
+            gb.bus.debugger.set_interrupt_breakpoint(*int, true);
+            self.print(format!("Breaking on {int}"));
+            return Ok(());
+        }
+        if arg.eq_ignore_ascii_case("stack") {
+            gb.bus.debugger.set_break_on_stack_imbalance(true);
+            self.print("Breaking on stack imbalance".to_string());
+            return Ok(());
+        }
+
+        let mut breakpoint =
+            parse_breakpoint(gb, arg).ok_or_else(|| format!("invalid breakpoint '{arg}'"))?;
+        if let Some(condition) = condition {
+            breakpoint = breakpoint.with_condition(condition);
+        }
+
+        self.print(format!(
+            "Breakpoint at {}",
+            describe_breakpoint(&gb.bus.symbols, &breakpoint)
+        ));
+        gb.bus.debugger.add_breakpoint(breakpoint);
+        Ok(())
+    }
+
+    fn command_trace(
+        &mut self,
+        gb: &mut GameBoy,
+        args: &str,
+        condition: Option<Expression>,
+    ) -> Result<(), String> {
+        let usage = || "usage: trace ADDR \"MESSAGE\" [if EXPR]".to_string();
+
+        let (arg, message) = args
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(usage)?;
+        let message = message
+            .trim()
+            .strip_prefix('"')
+            .and_then(|message| message.strip_suffix('"'))
+            .ok_or_else(usage)?;
+
+        let breakpoint =
+            parse_breakpoint(gb, arg).ok_or_else(|| format!("invalid address '{arg}'"))?;
+        let message =
+            Message::parse(message, &gb.bus.symbols).map_err(|e| format!("invalid message {e}"))?;
+        let mut tracepoint = breakpoint.with_action(HitAction::Log(message));
+        if let Some(condition) = condition {
+            tracepoint = tracepoint.with_condition(condition);
+        }
+
+        self.print(format!(
+            "Tracepoint at {}",
+            describe_breakpoint(&gb.bus.symbols, &tracepoint)
+        ));
+        gb.bus.debugger.add_breakpoint(tracepoint);
+        Ok(())
+    }
+
+    fn command_watch(
+        &mut self,
+        gb: &mut GameBoy,
+        args: &[&str],
+        condition: Option<Expression>,
+    ) -> Result<(), String> {
+        let usage = || "usage: watch ADDR|ADDR-ADDR|REGISTER [r|w|x] [VALUE]".to_string();
+
+        let target = args.first().ok_or_else(usage)?;
+        let access = match args.get(1).copied() {
+            None | Some("w") | Some("write") => Access::Write,
+            Some("r") | Some("read") => Access::Read,
+            Some("x") | Some("execute") => Access::Execute,
+            Some(_) => return Err(usage()),
+        };
+
+        let range = match target.split_once('-') {
+            Some((start, end)) => parse_address(gb, start)
+                .zip(parse_address(gb, end))
+                .filter(|(start, end)| start <= end)
+                .map(|(start, end)| start..=end),
+            None => parse_address(gb, target).map(|addr| addr..=addr),
+        }
+        .ok_or_else(|| format!("invalid address or range '{target}'"))?;
+
+        let mut watchpoint = Watchpoint::new(range, access);
+        if let Some(value) = args.get(2) {
+            let value = parse_number(value)
+                .and_then(|value| u8::try_from(value).ok())
+                .ok_or_else(|| format!("invalid value '{value}'"))?;
+            watchpoint = watchpoint.with_value(value);
+        }
+        if let Some(condition) = condition {
+            watchpoint = watchpoint.with_condition(condition);
+        }
+
+        self.print(format!("Watching {}", describe_watchpoint(&watchpoint)));
+        gb.bus.debugger.add_watchpoint(watchpoint);
+        Ok(())
+    }
+
+    fn command_delete(&mut self, gb: &mut GameBoy, args: &[&str]) -> Result<(), String> {
+        let Some(arg) = args.first() else {
+            gb.bus.debugger.clear();
+            self.print("Deleted all breakpoints and watchpoints".to_string());
+            return Ok(());
+        };
+
+        let index: usize = arg
+            .parse()
+            .map_err(|_| format!("invalid breakpoint number '{arg}'"))?;
+        let debugger = &mut gb.bus.debugger;
+        let breakpoint_count = debugger.breakpoints().len();
+
+        if index < breakpoint_count {
+            let breakpoint = debugger.remove_breakpoint(index);
+            self.print(format!(
+                "Deleted breakpoint at {}",
+                describe_breakpoint(&gb.bus.symbols, &breakpoint)
+            ));
+        } else if index - breakpoint_count < debugger.watchpoints().len() {
+            let watchpoint = debugger.remove_watchpoint(index - breakpoint_count);
+            self.print(format!(
+                "Deleted watchpoint on {}",
+                describe_watchpoint(&watchpoint)
+            ));
+        } else {
+            return Err(format!("no breakpoint or watchpoint numbered {index}"));
+        }
+        Ok(())
+    }
+
+    fn command_step(&mut self, gb: &mut GameBoy, args: &[&str]) -> Result<(), String> {
+        let count = match args.first() {
+            Some(arg) => arg
+                .parse()
+                .map_err(|_| format!("invalid number of steps '{arg}'"))?,
+            None => 1,
+        };
+
+        for _ in 0..count {
+            gb.step();
+            if let State::Locked { address, opcode } = gb.cpu.state() {
+                return Err(format!(
+                    "CPU locked up by illegal opcode {opcode:#04X} at {address:#06X}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn command_print(&mut self, gb: &mut GameBoy, args: &[&str]) -> Result<(), String> {
+        let single = match args {
+            [] => return Err("usage: print REGISTER|ADDR|EXPR".to_string()),
+            [arg] => Some(*arg),
+            _ => None,
+        };
+
+        if let Some(value) = single.and_then(|arg| cpu_register(gb, arg)) {
+            let name = single.unwrap_or_default().to_uppercase();
+            self.print(format!("{name} = {value:#06X} ({value})"));
+        } else if let Some(addr) = single.and_then(|arg| parse_address(gb, arg)) {
+            let value = gb.bus.read8(addr);
+            let name = debugger::register_name(addr).unwrap_or_default();
+            self.print(format!("[{addr:#06X}] {name} = {value:#04X} ({value})"));
+        } else {
+            let text = args.join(" ");
+            let value = Expression::parse(&text, &gb.bus.symbols)
+                .map_err(|e| format!("invalid expression {e}"))?
+                .eval(&gb.cpu, &gb.bus);
+            self.print(format!("{text} = {value:#X} ({value})"));
+        }
+        Ok(())
+    }
+
+    fn list_breakpoints(&mut self, gb: &GameBoy) {
+        let debugger = &gb.bus.debugger;
+        let mut lines: Vec<String> = debugger
+            .breakpoints()
+            .iter()
+            .map(|breakpoint| format!("break {}", describe_breakpoint(&gb.bus.symbols, breakpoint)))
+            .chain(
+                debugger
+                    .watchpoints()
+                    .iter()
+                    .map(|watchpoint| format!("watch {}", describe_watchpoint(watchpoint))),
+            )
+            .enumerate()
+            .map(|(i, line)| format!("#{i} {line}"))
+            .collect();
+        lines.extend(
+            INTERRUPTS
+                .iter()
+                .filter(|(_, int)| debugger.interrupt_breakpoint(*int))
+                .map(|(_, int)| format!("break on {int}")),
+        );
+        if debugger.break_on_stack_imbalance() {
+            lines.push("break on stack imbalance".to_string());
+        }
+
+        if lines.is_empty() {
+            self.print("No breakpoints or watchpoints".to_string());
+        }
+        lines.into_iter().for_each(|line| self.print(line));
+    }
+
+    /// Print the call stack tracked by the CPU, innermost frame first.
+    fn print_backtrace(&mut self, gb: &GameBoy) {
+        let call_stack = gb.cpu.call_stack();
+        if call_stack.depth() == 0 {
+            self.print("Call stack is empty".to_string());
+            return;
+        }
+
+        let symbols = &gb.bus.symbols;
+        let lines: Vec<String> = call_stack
+            .backtrace()
+            .take(OUTPUT_LINES - 1)
+            .enumerate()
+            .map(|(i, frame)| {
+                format!(
+                    "#{i} {} ({}) returning to {} [SP={:04X}]",
+                    symbols.describe(frame.target, frame.target_bank),
+                    frame.kind,
+                    symbols.describe(frame.return_address, frame.return_bank),
+                    frame.sp
+                )
+            })
+            .collect();
+        lines.into_iter().for_each(|line| self.print(line));
+
+        if call_stack.depth() > OUTPUT_LINES - 1 {
+            let more = call_stack.depth() - (OUTPUT_LINES - 1);
+            self.print(format!("... {more} more frames"));
+        }
+    }
+
+    /// Print the messages logged by tracepoints.
+    fn print_log(&mut self, gb: &mut GameBoy) {
+        for message in gb.bus.debugger.take_log() {
+            self.print(message);
+        }
+    }
+
+    fn print(&mut self, line: String) {
+        self.output.push(line);
+        if self.output.len() > OUTPUT_LINES {
+            self.output.remove(0);
+        }
+    }
+
+    pub fn draw(&mut self, stdout: &mut Stdout, gb: &GameBoy) -> crossterm::Result<()> {
+        stdout
+            .queue(style::SetForegroundColor(style::Color::White))?
+            .queue(style::SetBackgroundColor(style::Color::Black))?;
+
+        self.draw_disassembly(stdout, gb)?;
+        self.draw_registers(stdout, gb)?;
+        self.draw_stack(stdout, gb)?;
+        self.draw_memory(stdout, gb)?;
+
+        for (i, line) in self.output.iter().enumerate() {
+            print_line(stdout, 0, OUTPUT_Y + i as u16, WIDTH, line)?;
+        }
+        for i in self.output.len()..OUTPUT_LINES {
+            print_line(stdout, 0, OUTPUT_Y + i as u16, WIDTH, "")?;
+        }
+        let prompt_y = OUTPUT_Y + OUTPUT_LINES as u16;
+        print_line(stdout, 0, prompt_y, WIDTH, &format!("> {}", self.command))?;
+        stdout.queue(cursor::MoveTo(2 + self.command.len() as u16, prompt_y))?;
+
+        stdout.flush()
+    }
+
+    fn draw_disassembly(&mut self, stdout: &mut Stdout, gb: &GameBoy) -> crossterm::Result<()> {
+        let pc = gb.cpu.regs.pc;
+
+        // keep the disassembly in place while PC remains within it so that the instructions before PC stay visible
+        let mut lines = disassembly_lines(gb, self.disassembly_start);
+        let visible = lines[..lines.len() - 2]
+            .iter()
+            .any(|(address, _)| *address == Some(pc));
+        if !visible {
+            self.disassembly_start = pc;
+            lines = disassembly_lines(gb, pc);
+        }
+
+        for ((_, line), y) in lines.iter().zip(0..) {
+            print_line(stdout, 0, y, DISASSEMBLY_WIDTH, line)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_registers(&self, stdout: &mut Stdout, gb: &GameBoy) -> crossterm::Result<()> {
+        let regs = &gb.cpu.regs;
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        let state = match gb.cpu.state() {
+            State::Running => "running",
+            State::Halted => "halted",
+            State::Stopped => "stopped",
+            State::Locked { .. } => "locked",
+        };
+
+        let lines = [
+            format!(
+                "AF {:04X}  Flags {}{}{}{}",
+                regs.af(),
+                flag(regs.flags.zero(), 'Z'),
+                flag(regs.flags.subtraction(), 'N'),
+                flag(regs.flags.half_carry(), 'H'),
+                flag(regs.flags.carry(), 'C'),
+            ),
+            format!("BC {:04X}  LY    {:02X}", regs.bc(), gb.bus.read8(0xFF44)),
+            format!("DE {:04X}  IE    {:02X}", regs.de(), gb.bus.read8(0xFFFF)),
+            format!("HL {:04X}  IF    {:02X}", regs.hl(), gb.bus.read8(0xFF0F)),
+            format!("SP {:04X}", regs.sp),
+            format!("PC {:04X}  Bank  {:02X}", regs.pc, gb.bus.rom_bank(regs.pc)),
+            format!("CPU {state}"),
+            String::new(),
+        ];
+
+        for (line, y) in lines.iter().zip(0..REGISTER_LINES) {
+            print_line(stdout, SIDE_X, y, SIDE_WIDTH, line)?;
+        }
+        Ok(())
+    }
+
+    fn draw_stack(&self, stdout: &mut Stdout, gb: &GameBoy) -> crossterm::Result<()> {
+        let sp = gb.cpu.regs.sp;
+
+        for i in 0..DISASSEMBLY_LINES - REGISTER_LINES {
+            let addr = sp.wrapping_add(i * 2);
+            let value =
+                u16::from_le_bytes([gb.bus.read8(addr), gb.bus.read8(addr.wrapping_add(1))]);
+            // label values that could be return addresses
+            let label = match gb.bus.symbols.nearest(value, gb.bus.rom_bank(value)) {
+                Some(_) if value < 0x8000 => gb.bus.symbols.describe(value, gb.bus.rom_bank(value)),
+                _ => String::new(),
+            };
+            let line = format!(
+                "{addr:04X}  {value:04X} {}{label}",
+                if i == 0 { '>' } else { ' ' }
+            );
+            print_line(stdout, SIDE_X, REGISTER_LINES + i, SIDE_WIDTH, &line)?;
+        }
+        Ok(())
+    }
+
+    fn draw_memory(&self, stdout: &mut Stdout, gb: &GameBoy) -> crossterm::Result<()> {
+        for row in 0..MEMORY_LINES {
+            let addr = self.memory_start.wrapping_add(row * 16);
+            let bytes: Vec<u8> = (0..16)
+                .map(|i| gb.bus.read8(addr.wrapping_add(i)))
+                .collect();
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+            let ascii: String = bytes
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+                .collect();
+            let line = format!("{addr:04X}  {}  {ascii}", hex.join(" "));
+            print_line(stdout, 0, MEMORY_Y + row, WIDTH, &line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Disassemble the instructions from the given address onwards (preceded by their labels) as the lines of the
+/// disassembly pane, along with the address of the instruction on each line.
+fn disassembly_lines(gb: &GameBoy, mut address: u16) -> Vec<(Option<u16>, String)> {
+    let pc = gb.cpu.regs.pc;
+    let mut lines = Vec::new();
+
+    while lines.len() < DISASSEMBLY_LINES as usize {
+        let bank = gb.bus.rom_bank(address);
+        if let Some(label) = gb.bus.symbols.label(address, bank) {
+            lines.push((None, format!("{label}:")));
+        }
+
+        let instr = disassembler::disassemble(&gb.bus, address);
+        let breakpoint = gb
+            .bus
+            .debugger
+            .breakpoints()
+            .iter()
+            .any(|breakpoint| breakpoint.address == address);
+        let bytes: Vec<String> = instr.bytes.iter().map(|b| format!("{b:02X}")).collect();
+        let line = format!(
+            "{}{} {bank:02X}:{address:04X}  {:<9} {}",
+            if address == pc { '>' } else { ' ' },
+            if breakpoint { '*' } else { ' ' },
+            bytes.join(" "),
+            instr.text
+        );
+        lines.push((Some(address), line));
+
+        address = instr.next_address();
+    }
+
+    lines.truncate(DISASSEMBLY_LINES as usize);
+    lines
+}
+
+/// Print a line of text at the given position, padded (or truncated) to the given width so as to overwrite whatever
+/// was previously drawn there.
+fn print_line(
+    stdout: &mut Stdout,
+    x: u16,
+    y: u16,
+    width: u16,
+    text: &str,
+) -> crossterm::Result<()> {
+    let text: String = text.chars().take(width as usize).collect();
+    stdout
+        .queue(cursor::MoveTo(x, y))?
+        .queue(style::Print(format!(
+            "{text:width$}",
+            width = width as usize
+        )))?;
+    if x + width >= WIDTH {
+        stdout.queue(terminal::Clear(terminal::ClearType::UntilNewLine))?;
+    }
+    Ok(())
+}
+
+fn describe_breakpoint(symbols: &SymbolTable, breakpoint: &Breakpoint) -> String {
+    let location = match breakpoint.bank {
+        Some(bank) => format!("{bank:02X}:{:04X}", breakpoint.address),
+        None => format!("{:04X}", breakpoint.address),
+    };
+    let bank = breakpoint.bank.unwrap_or_default();
+    let mut s = match symbols.label(breakpoint.address, bank) {
+        Some(label) => format!("{label} ({location})"),
+        None => location,
+    };
+    s.push_str(&describe_hits(
+        &breakpoint.condition,
+        &breakpoint.action,
+        breakpoint.hits,
+    ));
+    s
+}
+
+fn describe_watchpoint(watchpoint: &Watchpoint) -> String {
+    let (start, end) = (*watchpoint.range.start(), *watchpoint.range.end());
+    let mut s = match debugger::register_name(start) {
+        Some(name) if start == end => name.to_string(),
+        _ if start == end => format!("{start:04X}"),
+        _ => format!("{start:04X}-{end:04X}"),
+    };
+    s.push_str(&format!(" ({})", watchpoint.access));
+    if let Some(value) = watchpoint.value {
+        s.push_str(&format!(" = {value:02X}"));
+    }
+    s.push_str(&describe_hits(
+        &watchpoint.condition,
+        &watchpoint.action,
+        watchpoint.hits,
+    ));
+    s
+}
+
+/// Split a command into the command itself and the condition given by a trailing `if` clause (e.g., `break 0150 if
+/// A == 3`). Only text after the closing quote of a tracepoint message is searched, as the message may itself contain
+/// "if".
+fn split_condition(command: &str) -> (&str, Option<&str>) {
+    let start = command.rfind('"').map_or(0, |i| i + 1);
+    match command[start..].rfind(" if ") {
+        Some(i) => (&command[..start + i], Some(&command[start + i + 4..])),
+        None => (command, None),
+    }
+}
+
+/// Describe the condition, action, and hit count shared by breakpoints and watchpoints.
+fn describe_hits(condition: &Option<Expression>, action: &HitAction, hits: u64) -> String {
+    let mut s = String::new();
+    if let HitAction::Log(message) = action {
+        s.push_str(&format!(" log \"{message}\""));
+    }
+    if let Some(condition) = condition {
+        s.push_str(&format!(" if {condition}"));
+    }
+    if hits > 0 {
+        s.push_str(&format!(" [{hits} hits]"));
+    }
+    s
+}
+
+/// Parse the location of a breakpoint given as a label, an address, or a bank and address (e.g., `01:4000`).
+fn parse_breakpoint(gb: &GameBoy, s: &str) -> Option<Breakpoint> {
+    if let Some(symbol) = gb.bus.symbols.get(s) {
+        Some(Breakpoint::at_symbol(symbol))
+    } else if let Some((bank, addr)) = s.split_once(':') {
+        parse_number(bank)
+            .zip(parse_address(gb, addr))
+            .map(|(bank, addr)| Breakpoint::with_bank(addr, bank))
+    } else {
+        parse_address(gb, s).map(Breakpoint::new)
+    }
+}
+
+/// Get the value of the CPU register with the given name (case insensitive).
+fn cpu_register(gb: &GameBoy, name: &str) -> Option<u16> {
+    let regs = &gb.cpu.regs;
+    let value = match name.to_ascii_lowercase().as_str() {
+        "a" => regs.a as u16,
+        "f" => regs.flags.0 as u16,
+        "b" => regs.b as u16,
+        "c" => regs.c as u16,
+        "d" => regs.d as u16,
+        "e" => regs.e as u16,
+        "h" => regs.h as u16,
+        "l" => regs.l as u16,
+        "af" => regs.af(),
+        "bc" => regs.bc(),
+        "de" => regs.de(),
+        "hl" => regs.hl(),
+        "sp" => regs.sp,
+        "pc" => regs.pc,
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Parse an address given either as a number, a label, the name of a 16-bit CPU register (the address it points to),
+/// or the name of an IO register.
+fn parse_address(gb: &GameBoy, s: &str) -> Option<u16> {
+    match s.to_ascii_lowercase().as_str() {
+        "bc" | "de" | "hl" | "sp" | "pc" => cpu_register(gb, s),
+        _ => gb
+            .bus
+            .symbols
+            .get(s)
+            .map(|symbol| symbol.address)
+            .or_else(|| parse_number(s))
+            .or_else(|| debugger::register_address(s)),
+    }
+}
+
+/// Parse a hexadecimal number, optionally prefixed with `$` or `0x`.
+fn parse_number(s: &str) -> Option<u16> {
+    let digits = s
+        .strip_prefix('$')
+        .or_else(|| s.strip_prefix("0x"))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).ok()
+}
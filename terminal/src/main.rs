@@ -1,8 +1,17 @@
+mod debug;
+
+use debug::{Action, DebugView};
+
 use rustyboy_core::{
     cartridge::Cartridge,
+    cheats::Cheats,
+    colourisation::{self, ButtonCombo},
+    debugger::BreakReason,
     joypad::Button,
     mbc,
+    palette::{ColourPalette, Preset, Rgb},
     screen::{Colour, SCREEN_HEIGHT, SCREEN_WIDTH},
+    symbols::{self, SymbolTable},
     GameBoy,
 };
 
@@ -25,10 +34,37 @@ use std::{
 fn main() -> crossterm::Result<()> {
     let args = Args::parse();
 
-    let cart = Cartridge::from_file(&args.rom).unwrap();
+    let mut cart = match &args.entry {
+        Some(entry) => Cartridge::from_archive_entry(&args.rom, entry),
+        None => Cartridge::from_file(&args.rom),
+    }
+    .unwrap();
+    match &args.patch {
+        Some(patch) => cart.apply_patch_file(patch).unwrap(),
+        None => cart.apply_patch_beside(&args.rom).unwrap(),
+    }
+    let palette = match (&args.palette_file, args.cgb_palette) {
+        (Some(path), _) => ColourPalette::from_file(path).unwrap(),
+        (None, Some(combo)) => combo.palette(),
+        (None, None) if args.colourise => colourisation::colourise(&cart),
+        (None, None) => args.palette.palette(),
+    };
+
     let mbc = mbc::from_cartridge(cart).unwrap();
 
-    let gb = GameBoy::new(mbc);
+    let mut gb = GameBoy::new(mbc);
+
+    if let Some(path) = &args.cheats {
+        gb.bus.cheats = Cheats::from_file(path).unwrap();
+    }
+
+    if let Some(path) = args
+        .symbols
+        .clone()
+        .or_else(|| symbols::find_symbols(&args.rom))
+    {
+        gb.bus.symbols = SymbolTable::from_file(path).unwrap();
+    }
 
     terminal::enable_raw_mode()?;
     std::io::stdout()
@@ -38,11 +74,17 @@ fn main() -> crossterm::Result<()> {
         .execute(terminal::Clear(terminal::ClearType::All))?;
 
     let mut emu = Emulator {
-        args,
         stdout: std::io::stdout(),
         gb,
+        palette,
         continue_execution: true,
+        debug: DebugView::new(),
+        debugging: false,
+        args,
     };
+    if emu.args.debug {
+        emu.enter_debug(None)?;
+    }
     let result = emu.run();
 
     terminal::disable_raw_mode()?;
@@ -57,7 +99,7 @@ fn main() -> crossterm::Result<()> {
 
 #[derive(Parser)]
 pub struct Args {
-    /// Path to a Game Boy ROM file to execute
+    /// Path to a Game Boy ROM file to execute (or a ZIP or gzip archive containing one)
     rom: PathBuf,
     /// Disable full RGB colours and use a more limited palette
     #[arg(long, default_value = "false")]
@@ -65,13 +107,47 @@ pub struct Args {
     /// Disable Unicode characters
     #[arg(long, default_value = "false")]
     no_unicode: bool,
+    /// Colour palette to display the screen with (dmg-green, pocket-grey, light, or high-contrast)
+    #[arg(long, default_value = "dmg-green")]
+    palette: Preset,
+    /// Load the colour palette from a file instead (overrides --palette)
+    #[arg(long)]
+    palette_file: Option<PathBuf>,
+    /// Colour the game as a Game Boy Color would (overrides --palette)
+    #[arg(long, default_value = "false")]
+    colourise: bool,
+    /// Colour the game with the Game Boy Color palette selected by holding the given button combination at boot (e.g.,
+    /// left+a or down+b; overrides --palette and --colourise)
+    #[arg(long)]
+    cgb_palette: Option<ButtonCombo>,
+    /// Load Game Genie and GameShark cheat codes from a file (one code per line, optionally followed by a description)
+    #[arg(long)]
+    cheats: Option<PathBuf>,
+    /// Apply an IPS, UPS, or BPS patch to the ROM (by default, a patch with the same name as the ROM is applied should
+    /// there be one)
+    #[arg(long)]
+    patch: Option<PathBuf>,
+    /// Name of the ROM to execute within a ZIP archive (by default, the first .gb, .gbc, or .sgb file in the archive)
+    #[arg(long)]
+    entry: Option<String>,
+    /// Load labels from an RGBDS or no$gmb symbol file (by default, a .sym file with the same name as the ROM is loaded
+    /// should there be one)
+    #[arg(long)]
+    symbols: Option<PathBuf>,
+    /// Start paused in debug mode (which can otherwise be entered by pressing Tab)
+    #[arg(long, default_value = "false")]
+    debug: bool,
 }
 
 struct Emulator {
     args: Args,
     stdout: Stdout,
     gb: GameBoy,
+    palette: ColourPalette,
     continue_execution: bool,
+    debug: DebugView,
+    /// Whether in debug mode, during which execution is paused.
+    debugging: bool,
 }
 
 impl Emulator {
@@ -82,8 +158,14 @@ impl Emulator {
             let delta = (Instant::now() - last_instant).as_secs_f32();
             last_instant = Instant::now();
 
-            self.gb.update(delta);
-            self.draw()?;
+            if !self.debugging {
+                let reason = self.gb.update_until_break(delta);
+                if reason != BreakReason::CycleLimit {
+                    self.enter_debug(Some(reason))?;
+                } else if self.gb.bus.gpu.screen.take_frame_ready() {
+                    self.draw()?;
+                }
+            }
             self.handle_events()?;
         }
 
@@ -106,32 +188,55 @@ impl Emulator {
         self.stdout.flush()
     }
 
+    /// Pause execution and show the debug view.
+    fn enter_debug(&mut self, reason: Option<BreakReason>) -> crossterm::Result<()> {
+        // release all buttons as key releases will be handled by the debug view instead
+        for button in BUTTONS {
+            self.gb.bus.joypad.set_button(button, false);
+        }
+
+        self.debugging = true;
+        self.debug.enter(&mut self.gb, reason);
+        self.stdout
+            .queue(terminal::Clear(terminal::ClearType::All))?;
+        self.debug.draw(&mut self.stdout, &self.gb)
+    }
+
+    fn leave_debug(&mut self) -> crossterm::Result<()> {
+        self.debugging = false;
+        self.stdout
+            .execute(terminal::Clear(terminal::ClearType::All))?;
+        self.draw()
+    }
+
     fn choose_character_and_colour(
         &self,
         term_x: u16,
         term_y: u16,
     ) -> (char, style::Color, style::Color) {
-        let up = self.gb.bus.gpu.screen.get(term_x as u8, term_y as u8 * 2);
-        let down = self
-            .gb
-            .bus
-            .gpu
-            .screen
-            .get(term_x as u8, term_y as u8 * 2 + 1);
+        let screen = &self.gb.bus.gpu.screen;
+        let up = screen.get_pixel(term_x as u8, term_y as u8 * 2);
+        let down = screen.get_pixel(term_x as u8, term_y as u8 * 2 + 1);
 
         let chr = if self.args.no_unicode {
-            colours_to_ascii(up, down)
+            colours_to_ascii(up.colour, down.colour)
         } else {
-            colours_to_unicode(up, down)
+            colours_to_unicode(up.colour, down.colour)
         };
 
         let (up_col, down_col) = if self.args.no_rgb {
-            (gb_colour_to_term_colour(up), gb_colour_to_term_colour(down))
+            (
+                gb_colour_to_term_colour(up.colour),
+                gb_colour_to_term_colour(down.colour),
+            )
         } else {
-            (gb_colour_to_rgb_colour(up), gb_colour_to_rgb_colour(down))
+            (
+                rgb_to_term_colour(self.palette.rgb(up.layer, up.colour)),
+                rgb_to_term_colour(self.palette.rgb(down.layer, down.colour)),
+            )
         };
 
-        let (fg, bg) = if up > down {
+        let (fg, bg) = if up.colour > down.colour {
             (down_col, up_col)
         } else {
             (up_col, down_col)
@@ -145,12 +250,15 @@ impl Emulator {
             match event::read()? {
                 Event::Key(KeyEvent { code, kind, .. }) => {
                     let down = !matches!(kind, KeyEventKind::Release);
-                    self.handle_key_event(code, down);
+                    self.handle_key_event(code, down)?;
                 }
 
                 Event::Resize(_, _) => {
                     self.stdout
                         .execute(terminal::Clear(terminal::ClearType::All))?;
+                    if self.debugging {
+                        self.debug.draw(&mut self.stdout, &self.gb)?;
+                    }
                 }
 
                 _ => {}
@@ -160,7 +268,19 @@ impl Emulator {
         Ok(())
     }
 
-    fn handle_key_event(&mut self, code: KeyCode, down: bool) {
+    fn handle_key_event(&mut self, code: KeyCode, down: bool) -> crossterm::Result<()> {
+        if self.debugging {
+            match code {
+                KeyCode::Esc => self.continue_execution = false,
+                _ if !down => {}
+                _ => match self.debug.handle_key(&mut self.gb, code) {
+                    Action::Stay => self.debug.draw(&mut self.stdout, &self.gb)?,
+                    Action::Continue => self.leave_debug()?,
+                },
+            }
+            return Ok(());
+        }
+
         match code {
             KeyCode::Char('x') => self.gb.bus.joypad.set_button(Button::A, down),
             KeyCode::Char('z') => self.gb.bus.joypad.set_button(Button::B, down),
@@ -170,14 +290,28 @@ impl Emulator {
             KeyCode::Down => self.gb.bus.joypad.set_button(Button::Down, down),
             KeyCode::Left => self.gb.bus.joypad.set_button(Button::Left, down),
             KeyCode::Right => self.gb.bus.joypad.set_button(Button::Right, down),
+            KeyCode::Char('r') if down => self.gb.reset(),
+            KeyCode::Tab if down => self.enter_debug(None)?,
             KeyCode::Esc => {
                 self.continue_execution = false;
             }
             _ => {}
         }
+        Ok(())
     }
 }
 
+const BUTTONS: [Button; 8] = [
+    Button::Start,
+    Button::Select,
+    Button::Down,
+    Button::Up,
+    Button::Left,
+    Button::Right,
+    Button::A,
+    Button::B,
+];
+
 fn colours_to_ascii(up: Colour, down: Colour) -> char {
     if up == down {
         ' '
@@ -207,27 +341,6 @@ fn gb_colour_to_term_colour(c: Colour) -> style::Color {
     }
 }
 
-fn gb_colour_to_rgb_colour(c: Colour) -> style::Color {
-    match c {
-        Colour::Black => style::Color::Rgb {
-            r: 15,
-            g: 56,
-            b: 15,
-        },
-        Colour::DarkGrey => style::Color::Rgb {
-            r: 48,
-            g: 98,
-            b: 48,
-        },
-        Colour::LightGrey => style::Color::Rgb {
-            r: 139,
-            g: 172,
-            b: 15,
-        },
-        Colour::White => style::Color::Rgb {
-            r: 155,
-            g: 188,
-            b: 15,
-        },
-    }
+fn rgb_to_term_colour([r, g, b]: Rgb) -> style::Color {
+    style::Color::Rgb { r, g, b }
 }
@@ -4,7 +4,15 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
-use rustyboy_core::{cartridge::Cartridge, mbc, GameBoy};
+use rustyboy_core::{
+    cartridge::Cartridge,
+    cheats::Cheats,
+    colourisation::{self, ButtonCombo},
+    mbc,
+    palette::{ColourPalette, Preset},
+    symbols::{self, SymbolTable},
+    GameBoy, Model,
+};
 
 pub async fn run() {
     env_logger::init();
@@ -16,23 +24,51 @@ pub async fn run() {
         .or_else(|| rfd::FileDialog::new().pick_file())
         .unwrap();
 
-    let cart = Cartridge::from_file(rom_path).unwrap();
+    let mut cart = match &args.entry {
+        Some(entry) => Cartridge::from_archive_entry(&rom_path, entry),
+        None => Cartridge::from_file(&rom_path),
+    }
+    .unwrap();
+    match &args.patch {
+        Some(patch) => cart.apply_patch_file(patch).unwrap(),
+        None => cart.apply_patch_beside(&rom_path).unwrap(),
+    }
     println!("Loaded cartridge: {}", cart);
 
+    let palette = match (&args.palette_file, args.cgb_palette) {
+        (Some(path), _) => ColourPalette::from_file(path).unwrap(),
+        (None, Some(combo)) => combo.palette(),
+        (None, None) if args.colourise => colourisation::colourise(&cart),
+        (None, None) => args.palette.palette(),
+    };
+
     let mbc = mbc::from_cartridge(cart).unwrap();
 
-    let gb = GameBoy::new(mbc);
+    let model = if args.sgb { Model::Sgb } else { Model::Dmg };
+    let mut gb = GameBoy::with_model(mbc, model);
+
+    if let Some(path) = &args.cheats {
+        gb.bus.cheats = Cheats::from_file(path).unwrap();
+    }
+
+    if let Some(path) = args
+        .symbols
+        .clone()
+        .or_else(|| symbols::find_symbols(&rom_path))
+    {
+        gb.bus.symbols = SymbolTable::from_file(path).unwrap();
+    }
 
     if let Some(_path) = &args.serial_log {
         unimplemented!() // TODO
     }
 
-    Emulator::new(gb, args.speed).await.run();
+    Emulator::new(gb, args.speed, palette).await.run();
 }
 
 #[derive(Parser)]
 pub struct Args {
-    /// Path to a Game Boy ROM file to execute
+    /// Path to a Game Boy ROM file to execute (or a ZIP or gzip archive containing one)
     rom: Option<PathBuf>,
     // Speed multiplier at which to run the emulator
     #[arg(short, long, default_value = "1.0")]
@@ -40,4 +76,35 @@ pub struct Args {
     /// Write the text written to serial out by debugging/test ROMs to a given file
     #[arg(long)]
     serial_log: Option<PathBuf>,
+    /// Colour palette to display the screen with (dmg-green, pocket-grey, light, or high-contrast)
+    #[arg(long, default_value = "dmg-green")]
+    palette: Preset,
+    /// Load the colour palette from a file instead (overrides --palette)
+    #[arg(long)]
+    palette_file: Option<PathBuf>,
+    /// Colour the game as a Game Boy Color would (overrides --palette)
+    #[arg(long, default_value = "false")]
+    colourise: bool,
+    /// Colour the game with the Game Boy Color palette selected by holding the given button combination at boot (e.g.,
+    /// left+a or down+b; overrides --palette and --colourise)
+    #[arg(long)]
+    cgb_palette: Option<ButtonCombo>,
+    /// Load Game Genie and GameShark cheat codes from a file (one code per line, optionally followed by a description)
+    #[arg(long)]
+    cheats: Option<PathBuf>,
+    /// Apply an IPS, UPS, or BPS patch to the ROM (by default, a patch with the same name as the ROM is applied should
+    /// there be one)
+    #[arg(long)]
+    patch: Option<PathBuf>,
+    /// Name of the ROM to execute within a ZIP archive (by default, the first .gb, .gbc, or .sgb file in the archive)
+    #[arg(long)]
+    entry: Option<String>,
+    /// Load labels from an RGBDS or no$gmb symbol file (by default, a .sym file with the same name as the ROM is loaded
+    /// should there be one)
+    #[arg(long)]
+    symbols: Option<PathBuf>,
+    /// Emulate the Super Game Boy, displaying the border and colours of SGB-enhanced games (overrides all palette
+    /// options)
+    #[arg(long, default_value = "false")]
+    sgb: bool,
 }
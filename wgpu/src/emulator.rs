@@ -4,7 +4,9 @@ use instant::Instant;
 use pixels::{Pixels, SurfaceTexture};
 use rustyboy_core::{
     joypad::Button,
-    screen::{Colour, SCREEN_HEIGHT, SCREEN_WIDTH},
+    palette::ColourPalette,
+    screen::{PixelFormat, SCREEN_HEIGHT, SCREEN_WIDTH},
+    sgb::{SGB_SCREEN_HEIGHT, SGB_SCREEN_WIDTH},
     GameBoy,
 };
 use winit::{
@@ -20,10 +22,11 @@ pub struct Emulator {
     pixels: Pixels,
     timer: Timer,
     emulation_speed: f32,
+    palette: ColourPalette,
 }
 
 impl Emulator {
-    pub async fn new(gb: GameBoy, emulation_speed: f32) -> Self {
+    pub async fn new(gb: GameBoy, emulation_speed: f32, palette: ColourPalette) -> Self {
         let event_loop = EventLoop::new();
 
         let window = Rc::new(
@@ -36,11 +39,17 @@ impl Emulator {
         #[cfg(target_arch = "wasm32")]
         crate::web::window_setup(Rc::clone(&window));
 
+        let (width, height) = if gb.bus.sgb.is_some() {
+            (SGB_SCREEN_WIDTH, SGB_SCREEN_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        };
+
         let pixels = {
             let window_size = window.inner_size();
             let surface_texture =
                 SurfaceTexture::new(window_size.width, window_size.height, window.as_ref());
-            Pixels::new_async(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, surface_texture)
+            Pixels::new_async(width as u32, height as u32, surface_texture)
                 .await
                 .expect("failed to initialise pixels")
         };
@@ -54,6 +63,7 @@ impl Emulator {
                 last_instant: Instant::now(),
             },
             emulation_speed,
+            palette,
         }
     }
 
@@ -85,22 +95,22 @@ impl Emulator {
 
         self.gb.update(delta * self.emulation_speed);
 
-        self.window.request_redraw();
+        if self.gb.bus.gpu.screen.take_frame_ready() {
+            self.window.request_redraw();
+        }
     }
 
     fn draw(&mut self) {
-        for (i, pixel) in self.pixels.frame_mut().chunks_exact_mut(4).enumerate() {
-            let x = (i % SCREEN_WIDTH) as u8;
-            let y = (i / SCREEN_WIDTH) as u8;
-
-            let rgba = match self.gb.bus.gpu.screen.get(x, y) {
-                Colour::Black => [15, 56, 15, 255],
-                Colour::DarkGrey => [48, 98, 48, 255],
-                Colour::LightGrey => [139, 172, 15, 255],
-                Colour::White => [155, 188, 15, 255],
-            };
-
-            pixel.copy_from_slice(&rgba);
+        let screen = &self.gb.bus.gpu.screen;
+
+        if let Some(sgb) = &self.gb.bus.sgb {
+            sgb.write_frame(screen, self.pixels.frame_mut(), PixelFormat::Rgba8888);
+        } else {
+            screen.write_frame(
+                self.pixels.frame_mut(),
+                PixelFormat::Rgba8888,
+                &self.palette,
+            );
         }
 
         self.pixels.render().unwrap();
@@ -143,9 +153,23 @@ impl Emulator {
             VirtualKeyCode::Down => self.gb.bus.joypad.set_button(Button::Down, down),
             VirtualKeyCode::Left => self.gb.bus.joypad.set_button(Button::Left, down),
             VirtualKeyCode::Right => self.gb.bus.joypad.set_button(Button::Right, down),
+            // second player (only read by Super Game Boy games that request multiple players)
+            VirtualKeyCode::G => self.set_player_2_button(Button::A, down),
+            VirtualKeyCode::F => self.set_player_2_button(Button::B, down),
+            VirtualKeyCode::Key2 => self.set_player_2_button(Button::Start, down),
+            VirtualKeyCode::Key1 => self.set_player_2_button(Button::Select, down),
+            VirtualKeyCode::W => self.set_player_2_button(Button::Up, down),
+            VirtualKeyCode::S => self.set_player_2_button(Button::Down, down),
+            VirtualKeyCode::A => self.set_player_2_button(Button::Left, down),
+            VirtualKeyCode::D => self.set_player_2_button(Button::Right, down),
+            VirtualKeyCode::R if down => self.gb.reset(),
             _ => {}
         };
     }
+
+    fn set_player_2_button(&mut self, button: Button, down: bool) {
+        self.gb.bus.joypad.set_player_button(1, button, down);
+    }
 }
 
 struct Timer {
@@ -2,7 +2,7 @@ use crate::emulator::Emulator;
 
 use std::rc::Rc;
 
-use rustyboy_core::{cartridge::Cartridge, mbc, GameBoy};
+use rustyboy_core::{archive, cartridge::Cartridge, mbc, palette::ColourPalette, GameBoy};
 
 use wasm_bindgen::{closure::Closure, JsCast};
 
@@ -12,11 +12,11 @@ pub async fn run() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
     if let Some(file) = rfd::AsyncFileDialog::new().pick_file().await {
-        let cart = Cartridge::from_data(file.read().await);
+        let cart = Cartridge::from_data(archive::read_rom(file.read().await).unwrap());
         let mbc = mbc::from_cartridge(cart).unwrap();
         let gb = GameBoy::new(mbc);
 
-        Emulator::new(gb, 1.0).await.run();
+        Emulator::new(gb, 1.0, ColourPalette::default()).await.run();
     }
 }
 